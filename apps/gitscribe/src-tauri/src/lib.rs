@@ -1,286 +1,1130 @@
-use gitscribe_core::jira::JiraConfig;
+use gitscribe_core::cache::{self, CachedGeneration, GenerationCache, JiraCache};
+use gitscribe_core::chat::ChatSession;
+use gitscribe_core::confluence::{self, PageOutcome, PageTarget};
+use gitscribe_core::credentials::{self, CredentialSource};
+use gitscribe_core::git_notes::{self, NotesStore, StoreOutcome};
+use gitscribe_core::grounding::{self, GroundingReport, Source};
+use gitscribe_core::jira::{self, JiraConfig, JiraWriteOutcome, WriteBack};
+use gitscribe_core::layout;
+use gitscribe_core::notify::{self, Announcement, NotifyOutcome, WebhookFormat};
+use gitscribe_core::prompt::{self, PromptVars};
+use gitscribe_core::publish::{
+    self, Attachment, ForgeConfig, ForgeKind, PublishOutcome, ReleaseDraft,
+};
+use gitscribe_core::redact::Redactor;
+use gitscribe_core::render;
+use gitscribe_core::report::GenerationReport;
+use gitscribe_core::structured::{self, NotesFormat, ReleaseNotes};
+use gitscribe_core::style::{self, StyleSettings};
+use gitscribe_core::templates::{self, TemplateInfo};
+use gitscribe_core::translate::{self, Glossary, TranslationCheck};
+use gitscribe_core::validate::{self, ValidationRules};
 use gitscribe_core::{
-    call_ollama, generate_context, list_git_refs, list_ollama_models, read_file_content,
+    call_ollama, generate_context_with_stats, list_git_refs, list_ollama_models, read_file_content,
+    CancellationToken, OllamaOptions, OllamaResponse, PathFilter,
 };
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tauri::menu::{Menu, MenuItem, PredefinedMenuItem, Submenu};
 use tauri::{AppHandle, Emitter, Manager, Window};
 
-const DEFAULT_PROMPT: &str = r##"# Role & Objective
-You are an expert **Technical Product Manager** and **Strategic Communications Lead**.
-Your mission is to transform raw technical input (adhoc notes, commit logs, diffs) into **world-class Release Notes** written for end users. Your writing must highlight product value, usability improvements, feature benefits, and resolved issues in clear human-friendly language.
-
-Your output must be truthful, concise, and formatted using the template below with no deviations.
+/// Copies templates from the app config directory used by earlier versions
+/// into the shared user templates directory.
+fn import_old_templates(app: &AppHandle) {
+    if let Ok(app_dir) = app.path().app_config_dir() {
+        let _ = templates::import_templates(&app_dir.join("templates"));
+    }
+}
 
----
+fn open_templates_folder(app: &AppHandle) {
+    import_old_templates(app);
+    if let Ok(path) = templates::create_user_templates_dir() {
+        let _ = open::that(path);
+    }
+}
 
-# Audience Focus — End Users Only
+#[tauri::command]
+fn list_templates_cmd(app: AppHandle) -> Result<Vec<TemplateInfo>, String> {
+    import_old_templates(&app);
+    templates::list_templates().map_err(|e| e.to_string())
+}
 
-Write for everyday users who care about:
+#[tauri::command]
+fn load_template_cmd(name: String) -> Result<String, String> {
+    templates::load_template(&name).map_err(|e| e.to_string())
+}
 
-- What’s new?
-- What got better?
-- What problems are now fixed?
-- Why should they update?
+#[tauri::command]
+fn load_file_cmd(file_path: String) -> Result<String, String> {
+    read_file_content(Some(&file_path), "User Notes")
+        .map_err(|e| e.to_string())
+        .and_then(|opt| opt.ok_or_else(|| "File not found or empty".to_string()))
+}
 
-Avoid internal terminology, engineering detail, and operational risk language unless necessary for clarity.
+#[tauri::command]
+fn save_file_cmd(file_path: String, content: String) -> Result<(), String> {
+    fs::write(file_path, content).map_err(|e| e.to_string())
+}
 
----
+#[tauri::command]
+fn get_repo_refs_cmd(repo_path: String) -> Result<Vec<String>, String> {
+    list_git_refs(Some(Path::new(&repo_path))).map_err(|e| e.to_string())
+}
 
-# Input You Will Receive
+#[tauri::command]
+async fn get_ollama_models_cmd() -> Result<Vec<String>, String> {
+    let url = "http://localhost:11434";
+    list_ollama_models(url).await.map_err(|e| e.to_string())
+}
 
-1. **Adhoc Notes** — product vision, strategic messaging anchors (*highest priority*).
-2. **Commit Logs** — chronological history of changes.
-3. **Code Diff** — raw modifications used to validate behavior and user impact.
+const KEYRING_SERVICE: &str = "gitscribe";
+const KEYRING_JIRA_PAT: &str = "jira-pat";
+const KEYRING_WEBHOOK_URL: &str = "webhook-url";
 
-If Adhoc Notes conflict with commit/diff details, prioritize Adhoc Notes and use commits/diffs only for evidence and detail expansion.
+fn keyring_entry(name: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, name).map_err(|e| e.to_string())
+}
 
----
+fn keyring_secret(name: &str) -> Option<String> {
+    keyring_entry(name).ok().and_then(|e| e.get_password().ok())
+}
 
-# Mandatory Output Behaviors
+/// Stores a secret in the system keyring; an empty one removes the entry.
+fn save_keyring_secret(name: &str, secret: &str, label: &str) -> Result<(), String> {
+    let entry = keyring_entry(name)?;
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!(
+                "Failed to remove the {} from the keyring: {}",
+                label, e
+            )),
+        };
+    }
+    entry
+        .set_password(secret)
+        .map_err(|e| format!("Failed to store the {} in the keyring: {}", label, e))
+}
 
-### 1. Translate Technical Changes Into End-User Benefit
-Everything included must answer the user's question: **"Why does this matter to me?"**
+/// The Jira token from the system keyring, else the environment or token
+/// file. The frontend never sees it.
+fn jira_pat() -> Option<(String, CredentialSource)> {
+    if let Some(pat) = keyring_secret(KEYRING_JIRA_PAT) {
+        return Some((pat, CredentialSource::Keyring));
+    }
+    credentials::jira_pat_from_env_or_file(None).unwrap_or_else(|e| {
+        println!("Ignoring the Jira token file: {:#}", e);
+        None
+    })
+}
 
-- ❌ "Refactored AuthenticationFactory."
-- ❌ "Fix for issue #402"
-- ✔ "Fixed a login failure affecting some users with MFA enabled (Issue #402)."
+/// Stores the Jira token in the system keyring; an empty token removes it.
+#[tauri::command]
+fn save_jira_pat_cmd(pat: String) -> Result<(), String> {
+    save_keyring_secret(KEYRING_JIRA_PAT, &pat, "Jira token")
+}
 
-If no user value exists → exclude the item.
+/// Where the Jira token is found, e.g. "the system keyring", or `None`.
+#[tauri::command]
+fn jira_pat_source_cmd() -> Option<String> {
+    jira_pat().map(|(_, source)| source.to_string())
+}
 
----
+/// Keyring entry of the access token for `kind`.
+fn keyring_forge_token(kind: ForgeKind) -> &'static str {
+    match kind {
+        ForgeKind::GitHub => "github-token",
+        ForgeKind::GitLab => "gitlab-token",
+    }
+}
 
-### 2. Noise Filtering — Exclude All Non-User-Facing Work
+/// The forge access token from the system keyring, else `GITHUB_TOKEN` or
+/// `GITLAB_TOKEN`. Like the Jira token, it never reaches the frontend.
+fn forge_token(kind: ForgeKind) -> Option<(String, CredentialSource)> {
+    if let Some(token) = keyring_secret(keyring_forge_token(kind)) {
+        return Some((token, CredentialSource::Keyring));
+    }
+    std::env::var(kind.token_env())
+        .ok()
+        .filter(|t| !t.trim().is_empty())
+        .map(|t| (t, CredentialSource::Environment(kind.token_env())))
+}
 
-Do **not** mention:
+/// Stores the access token for `forge` in the system keyring; an empty token
+/// removes it.
+#[tauri::command]
+fn save_forge_token_cmd(forge: String, token: String) -> Result<(), String> {
+    let kind = ForgeKind::from_str(&forge).map_err(|e| e.to_string())?;
+    save_keyring_secret(keyring_forge_token(kind), &token, "access token")
+}
 
-- Whitespace, formatting, spelling changes
-- Comment-only or rename-only commits
-- Internal refactors with no user experience impact
-- CI/CD, build scripts, test files, packaging changes
+/// Where the access token for `forge` is found, or `None`.
+#[tauri::command]
+fn forge_token_source_cmd(forge: String) -> Option<String> {
+    let kind = ForgeKind::from_str(&forge).ok()?;
+    forge_token(kind).map(|(_, source)| source.to_string())
+}
 
-When uncertain → omit rather than speculate.
+/// Stores the chat webhook URL in the system keyring, as its path usually
+/// holds a secret; an empty URL removes it.
+#[tauri::command]
+fn save_webhook_url_cmd(url: String) -> Result<(), String> {
+    save_keyring_secret(KEYRING_WEBHOOK_URL, &url, "webhook URL")
+}
 
----
+/// Host of the stored webhook URL, or `None`. The full URL never reaches the
+/// frontend.
+#[tauri::command]
+fn webhook_host_cmd() -> Option<String> {
+    keyring_secret(KEYRING_WEBHOOK_URL).map(|url| notify::host(&url).to_string())
+}
 
-### 3. Interpreting Diffs (When Intent Is Unclear)
+fn build_jira_config(jira_url: Option<String>, jira_cache_ttl: Option<u64>) -> Option<JiraConfig> {
+    let url = jira_url.filter(|u| !u.is_empty())?;
+    let (pat, _) = jira_pat()?;
+    let cache_ttl = jira_cache_ttl
+        .map(Duration::from_secs)
+        .unwrap_or(jira::DEFAULT_CACHE_TTL);
+    Some(JiraConfig {
+        url,
+        pat,
+        cache_ttl,
+    })
+}
 
-Use diffs to infer *behavior*, not code detail.
+#[tauri::command]
+fn clear_jira_cache_cmd() -> Result<usize, String> {
+    JiraCache::clear_all().map_err(|e| e.to_string())
+}
 
-Look for changes that may indicate:
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn generate_preview_cmd(
+    repo_path: String,
+    start: String,
+    end: String,
+    notes: String,
+    paths: Option<Vec<String>>,
+    context_template: Option<String>,
+    jira_url: Option<String>,
+    jira_cache_ttl: Option<u64>,
+    stored_notes_ref: Option<String>,
+    redact: Option<bool>,
+    redact_patterns: Option<Vec<String>>,
+    style: Option<StyleSettings>,
+    template: Option<String>,
+) -> Result<String, String> {
+    let jira_config = build_jira_config(jira_url, jira_cache_ttl);
+    let previous_notes = stored_notes(&repo_path, &start, stored_notes_ref);
+    let style = style_settings(style, template.iter())?;
+    let redactor = build_redactor(redact, redact_patterns)?;
 
-- Crashes fixed
-- UI/UX behavior changes
-- Input validation or error handling improvements
-- Faster performance or smoother interactions
-- More reliable workflows
+    generate_context_with_stats(
+        &start,
+        &end,
+        Some(notes),
+        &PathFilter::from_specs(&paths.unwrap_or_default()),
+        Some(Path::new(&repo_path)),
+        jira_config,
+        context_template.as_deref().filter(|t| !t.trim().is_empty()),
+        previous_notes.as_deref(),
+        Some(&style),
+        redactor.as_ref(),
+    )
+    .await
+    .map(|(context, _)| context)
+    .map_err(|e| format!("{:#}", e))
+}
 
-Do not invent impact or performance metrics. If unsure, describe conservatively or exclude.
+/// The style example settings for a run: the app's defaults overridden by the
+/// templates' own settings, which must agree as they share one context.
+fn style_settings<'a>(
+    defaults: Option<StyleSettings>,
+    templates: impl Iterator<Item = &'a String>,
+) -> Result<StyleSettings, String> {
+    let templates: Vec<&str> = templates.map(String::as_str).collect();
+    style::settings_for_templates(&defaults.unwrap_or_default(), &templates)
+        .map_err(|e| format!("{:#}", e))
+}
 
----
+/// Masks secrets and personal data in the context unless `redact` is false.
+fn build_redactor(
+    redact: Option<bool>,
+    patterns: Option<Vec<String>>,
+) -> Result<Option<Redactor>, String> {
+    if !redact.unwrap_or(true) {
+        return Ok(None);
+    }
+    Redactor::builtin()
+        .with_patterns(&patterns.unwrap_or_default())
+        .map(Some)
+        .map_err(|e| format!("{:#}", e))
+}
 
-### 4. Security Mentions (Only If User-Relevant)
+/// Notes stored in the repository for `start` when `notes_ref` is given, for
+/// use as context.
+fn stored_notes(repo_path: &str, start: &str, notes_ref: Option<String>) -> Option<String> {
+    let notes_ref = notes_ref.filter(|r| !r.trim().is_empty())?;
+    git_notes::read_stored_notes(start, &notes_ref, Some(Path::new(repo_path))).map(|s| s.text)
+}
 
-If a change clearly affects security in a way that users should know (e.g., patched vulnerability, improved data protection), include it.
+#[derive(Serialize)]
+struct GenerationResult {
+    output: String,
+    cached: bool,
+    cancelled: bool,
+    report: GenerationReport,
+}
 
-Format CVEs as `[CVE-YYYY-XXXX]`.
+/// Token for the generation currently in flight, if any, and the context of
+/// the last generation so it can be refined afterwards.
+#[derive(Default)]
+struct GenerationState {
+    cancel: Mutex<Option<CancellationToken>>,
+    last_context: Mutex<Option<String>>,
+    /// Commits and issues of the last context, for reviewing the notes.
+    last_sources: Mutex<Vec<Source>>,
+}
 
-Do not include exploit details, PoC code, or speculation.
+/// The active refinement conversation, if any.
+#[derive(Default)]
+struct ChatState {
+    session: Mutex<Option<ChatSession>>,
+}
 
----
+#[tauri::command]
+fn cancel_generation_cmd(state: tauri::State<'_, GenerationState>) -> bool {
+    match state.cancel.lock().unwrap().take() {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
 
-### 5. Tone & Style Requirements
+/// Settings shared by every generation in a run.
+struct NotesJob {
+    model: String,
+    options: OllamaOptions,
+    structured: bool,
+    notes_format: NotesFormat,
+    use_cache: bool,
+    auto_repair: bool,
+}
 
-- Professional, clear, friendly, value-focused.
-- No jargon unless unavoidable — prioritize plain language.
-- Use active voice: *You can now…*, *This update makes it easier to…*
-- Never exaggerate or fabricate details not present in the input.
+impl NotesJob {
+    fn new(
+        model: String,
+        ollama_options: Option<OllamaOptions>,
+        structured: Option<bool>,
+        output_format: Option<String>,
+        no_cache: Option<bool>,
+        auto_repair: Option<bool>,
+    ) -> Result<Self, String> {
+        let structured = structured.unwrap_or(false);
+        let notes_format = match output_format {
+            Some(format) => NotesFormat::from_str(&format).map_err(|e| e.to_string())?,
+            None => NotesFormat::default(),
+        };
+        let mut options = ollama_options.unwrap_or_default();
+        if structured {
+            options.format = Some(structured::schema());
+        }
+        Ok(Self {
+            model,
+            options,
+            structured,
+            notes_format,
+            use_cache: !no_cache.unwrap_or(false),
+            auto_repair: auto_repair.unwrap_or(false),
+        })
+    }
+}
 
----
+/// Builds the context and a report for it, remembering the context for refinement.
+#[allow(clippy::too_many_arguments)]
+async fn build_context(
+    state: &GenerationState,
+    repo_path: &str,
+    start: &str,
+    end: &str,
+    notes: String,
+    paths: Option<Vec<String>>,
+    context_template: Option<String>,
+    jira_config: Option<JiraConfig>,
+    previous_notes: Option<String>,
+    style: StyleSettings,
+    redactor: Option<Redactor>,
+) -> Result<(String, GenerationReport), String> {
+    let context_timer = Instant::now();
+    let (context, context_stats) = generate_context_with_stats(
+        start,
+        end,
+        Some(notes),
+        &PathFilter::from_specs(&paths.unwrap_or_default()),
+        Some(Path::new(repo_path)),
+        jira_config,
+        context_template.as_deref().filter(|t| !t.trim().is_empty()),
+        previous_notes.as_deref(),
+        Some(&style),
+        redactor.as_ref(),
+    )
+    .await
+    .map_err(|e| format!("{:#}", e))?;
 
-# Output Template (Use Exactly As Written)
+    *state.last_context.lock().unwrap() = Some(context.clone());
+    *state.last_sources.lock().unwrap() = context_stats.sources.clone();
 
-If any section has no content, write: **"None in this release."**
+    let mut report = GenerationReport::new(start, end, &context, context_stats);
+    report.context_ms = context_timer.elapsed().as_millis() as u64;
+    Ok((context, report))
+}
 
-```markdown
-# [Product Name] [Version]
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn generate_ai_cmd(
+    window: Window,
+    state: tauri::State<'_, GenerationState>,
+    repo_path: String,
+    start: String,
+    end: String,
+    notes: String,
+    model: String,
+    system_prompt: Option<String>,
+    ollama_options: Option<OllamaOptions>,
+    structured: Option<bool>,
+    output_format: Option<String>,
+    paths: Option<Vec<String>>,
+    context_template: Option<String>,
+    jira_url: Option<String>,
+    jira_cache_ttl: Option<u64>,
+    no_cache: Option<bool>,
+    auto_repair: Option<bool>,
+    stored_notes_ref: Option<String>,
+    redact: Option<bool>,
+    redact_patterns: Option<Vec<String>>,
+    style: Option<StyleSettings>,
+    template: Option<String>,
+) -> Result<GenerationResult, String> {
+    let jira_config = build_jira_config(jira_url, jira_cache_ttl);
+    let job = NotesJob::new(
+        model,
+        ollama_options,
+        structured,
+        output_format,
+        no_cache,
+        auto_repair,
+    )?;
+    let (context, report) = build_context(
+        &state,
+        &repo_path,
+        &start,
+        &end,
+        notes,
+        paths,
+        context_template,
+        jira_config,
+        stored_notes(&repo_path, &start, stored_notes_ref),
+        style_settings(style, template.iter())?,
+        build_redactor(redact, redact_patterns)?,
+    )
+    .await?;
 
-**Release Date:** [YYYY-MM-DD]
+    let cancel = CancellationToken::new();
+    *state.cancel.lock().unwrap() = Some(cancel.clone());
+    let result = generate_notes(&window, &job, &context, system_prompt, report, &cancel).await;
+    state.cancel.lock().unwrap().take();
+    result
+}
 
-## Executive Summary
-[2–4 sentence high-level overview of what this release delivers and why it matters.]
+/// A named system prompt for a multi-audience run.
+#[derive(Deserialize)]
+struct AudiencePrompt {
+    name: String,
+    system_prompt: Option<String>,
+    /// The unrendered template, for its style example settings.
+    template: Option<String>,
+}
 
-## New Features
-- **[Feature Name]** — [Describe the value to users. Use "You can now…" when appropriate.]
+#[derive(Serialize)]
+struct AudienceResult {
+    name: String,
+    result: GenerationResult,
+}
 
-## Improvements
-- **[Area/Workflow/Experience]** — [Describe enhancement in user-benefit terms.]
+/// Builds the context once and generates notes for each audience in turn.
+/// An `ai-audience` event announces each audience before its tokens stream.
+/// Cancelling stops the current audience and skips the rest.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn generate_audiences_cmd(
+    window: Window,
+    state: tauri::State<'_, GenerationState>,
+    repo_path: String,
+    start: String,
+    end: String,
+    notes: String,
+    model: String,
+    audiences: Vec<AudiencePrompt>,
+    ollama_options: Option<OllamaOptions>,
+    structured: Option<bool>,
+    output_format: Option<String>,
+    paths: Option<Vec<String>>,
+    context_template: Option<String>,
+    jira_url: Option<String>,
+    jira_cache_ttl: Option<u64>,
+    no_cache: Option<bool>,
+    auto_repair: Option<bool>,
+    stored_notes_ref: Option<String>,
+    redact: Option<bool>,
+    redact_patterns: Option<Vec<String>>,
+    style: Option<StyleSettings>,
+) -> Result<Vec<AudienceResult>, String> {
+    let jira_config = build_jira_config(jira_url, jira_cache_ttl);
+    let job = NotesJob::new(
+        model,
+        ollama_options,
+        structured,
+        output_format,
+        no_cache,
+        auto_repair,
+    )?;
+    let (context, base_report) = build_context(
+        &state,
+        &repo_path,
+        &start,
+        &end,
+        notes,
+        paths,
+        context_template,
+        jira_config,
+        stored_notes(&repo_path, &start, stored_notes_ref),
+        style_settings(style, audiences.iter().filter_map(|a| a.template.as_ref()))?,
+        build_redactor(redact, redact_patterns)?,
+    )
+    .await?;
+
+    let cancel = CancellationToken::new();
+    *state.cancel.lock().unwrap() = Some(cancel.clone());
+    let mut results = Vec::new();
+    for audience in audiences {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let _ = window.emit("ai-audience", &audience.name);
+        let mut report = base_report.clone();
+        report.audience = Some(audience.name.clone());
+        let result = generate_notes(
+            &window,
+            &job,
+            &context,
+            audience.system_prompt,
+            report,
+            &cancel,
+        )
+        .await;
+        match result {
+            Ok(result) => results.push(AudienceResult {
+                name: audience.name,
+                result,
+            }),
+            Err(e) => {
+                state.cancel.lock().unwrap().take();
+                return Err(format!("{}: {}", audience.name, e));
+            }
+        }
+    }
+    state.cancel.lock().unwrap().take();
+    Ok(results)
+}
 
-## Bug Fixes
-- **[Issue/Component/Symptom]** — [Describe the resolved problem in user-language.] (Issue #[ID])
+/// Generates notes for one system prompt against a prepared context,
+/// streaming tokens to the window and reusing cached results.
+async fn generate_notes(
+    window: &Window,
+    job: &NotesJob,
+    context: &str,
+    system_prompt: Option<String>,
+    mut report: GenerationReport,
+    cancel: &CancellationToken,
+) -> Result<GenerationResult, String> {
+    let model = &job.model;
+    let options = &job.options;
+    report.set_system_prompt(system_prompt.as_deref());
+    report.model = Some(model.clone());
+    report.options = Some(options.clone());
+
+    // Structured runs cache the raw JSON; render it for display
+    let render = |raw: &str| -> Result<String, String> {
+        if job.structured {
+            ReleaseNotes::parse(raw)
+                .map(|notes| notes.render(job.notes_format))
+                .map_err(|e| e.to_string())
+        } else {
+            Ok(raw.to_string())
+        }
+    };
 
-## Security Notes (User-Relevant Only)
-- [CVE-ID or "None"]: [Short explanation of user impact — no technical exploit details.]
+    let cache = if job.use_cache {
+        GenerationCache::default_location().ok()
+    } else {
+        None
+    };
+    let cache_key = GenerationCache::key(
+        context,
+        system_prompt.as_deref(),
+        model,
+        &options.sampling(),
+    );
 
-## Deprecations / Removed Functionality
-- [List removed or changed features only if relevant to user behavior.]
-- If none → **"None in this release."**
-```
-"##;
+    let url = "http://localhost:11434/api/generate";
+    let validate_markdown = !job.structured || job.notes_format == NotesFormat::Markdown;
+
+    if let Some(hit) = cache.as_ref().and_then(|c| c.get(&cache_key)) {
+        let output = render(&hit.output)?;
+        let _ = window.emit("ai-token", &output);
+        report.cache_hit = true;
+        let output = if validate_markdown {
+            validate_output(
+                output,
+                context,
+                system_prompt.as_deref(),
+                job,
+                cancel,
+                &mut report,
+            )
+            .await?
+        } else {
+            output
+        };
+        return Ok(GenerationResult {
+            output,
+            cached: true,
+            cancelled: false,
+            report,
+        });
+    }
 
-fn ensure_templates_dir(app: &AppHandle) -> Result<std::path::PathBuf, String> {
-    let app_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
-    let templates_dir = app_dir.join("templates");
+    let generation_timer = Instant::now();
+    let response = if job.structured {
+        structured::generate_structured(
+            model,
+            url,
+            context,
+            system_prompt.as_ref(),
+            options,
+            2,
+            Some(cancel),
+        )
+        .await
+        .map(|generated| match generated {
+            Some(generated) => OllamaResponse {
+                text: generated.raw,
+                stats: generated.stats,
+                cancelled: false,
+            },
+            None => OllamaResponse {
+                cancelled: true,
+                ..Default::default()
+            },
+        })
+    } else {
+        // Create a callback that emits events to the window
+        let window = window.clone();
+        let callback = move |token: &str| {
+            let _ = window.emit("ai-token", token);
+        };
+
+        call_ollama(
+            model,
+            url,
+            context,
+            system_prompt.as_ref(),
+            options,
+            Some(callback),
+            Some(cancel),
+        )
+        .await
+    }
+    .map_err(|e| e.to_string())?;
+    let raw_output = response.text;
+
+    report.generation_ms = generation_timer.elapsed().as_millis() as u64;
+    report.ollama = response.stats;
+    report.cancelled = cancel.is_cancelled();
+
+    if cancel.is_cancelled() {
+        return Ok(GenerationResult {
+            output: raw_output,
+            cached: false,
+            cancelled: true,
+            report,
+        });
+    }
 
-    if !templates_dir.exists() {
-        fs::create_dir_all(&templates_dir).map_err(|e| e.to_string())?;
+    let output = render(&raw_output)?;
+    if job.structured {
+        // Nothing was streamed while waiting for the JSON, so send the result in one go
+        let _ = window.emit("ai-token", &output);
     }
 
-    // Ensure default template exists
-    let default_path = templates_dir.join("default.md");
-    if !default_path.exists() {
-        fs::write(&default_path, DEFAULT_PROMPT).map_err(|e| e.to_string())?;
+    if let Some(cache) = &cache {
+        let entry = CachedGeneration {
+            start: report.start.clone(),
+            end: report.end.clone(),
+            model: model.clone(),
+            context: context.to_string(),
+            output: raw_output,
+            created_at: cache::now_secs(),
+        };
+        let _ = cache.put(&cache_key, &entry);
     }
 
-    Ok(templates_dir)
+    let output = if validate_markdown {
+        validate_output(
+            output,
+            context,
+            system_prompt.as_deref(),
+            job,
+            cancel,
+            &mut report,
+        )
+        .await?
+    } else {
+        output
+    };
+
+    Ok(GenerationResult {
+        output,
+        cached: false,
+        cancelled: false,
+        report,
+    })
 }
 
-fn open_templates_folder(app: &AppHandle) {
-    if let Ok(path) = ensure_templates_dir(app) {
-        let _ = open::that(path);
+/// Checks the notes against the output template in the system prompt and,
+/// with `auto_repair`, asks the model once to fix any problems it found.
+/// Structured notes get their headings from the schema, so those are not
+/// checked, and they are never rewritten as free-form text.
+async fn validate_output(
+    output: String,
+    context: &str,
+    system_prompt: Option<&str>,
+    job: &NotesJob,
+    cancel: &CancellationToken,
+    report: &mut GenerationReport,
+) -> Result<String, String> {
+    let mut rules = ValidationRules::from_prompt(system_prompt);
+    if job.structured {
+        rules = rules.without_headings();
+    }
+    report.violations = rules.validate(&output, context);
+    if report.violations.is_empty() || !job.auto_repair || job.structured {
+        return Ok(output);
     }
-}
 
-#[tauri::command]
-fn list_templates_cmd(app: AppHandle) -> Result<Vec<String>, String> {
-    let dir = ensure_templates_dir(&app)?;
-    let mut templates = Vec::new();
-
-    let entries = fs::read_dir(dir).map_err(|e| e.to_string())?;
-    for entry in entries {
-        let entry = entry.map_err(|e| e.to_string())?;
-        let path = entry.path();
-        if path.is_file() {
-            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                if name.ends_with(".md") || name.ends_with(".txt") {
-                    templates.push(name.to_string());
-                }
-            }
-        }
+    let mut session = ChatSession::new(
+        &job.model,
+        "http://localhost:11434",
+        system_prompt,
+        context,
+        OllamaOptions {
+            format: None,
+            ..job.options.clone()
+        },
+    )
+    .with_draft(&output);
+    let instruction = validate::repair_instruction(&report.violations);
+    let response = session
+        .send(Some(&instruction), |_: &str| {}, Some(cancel))
+        .await
+        .map_err(|e| e.to_string())?;
+    if response.cancelled {
+        // Keep the original notes and their violations
+        return Ok(output);
     }
-    Ok(templates)
-}
 
-#[tauri::command]
-fn load_template_cmd(app: AppHandle, filename: String) -> Result<String, String> {
-    let dir = ensure_templates_dir(&app)?;
-    let path = dir.join(filename);
-    fs::read_to_string(path).map_err(|e| e.to_string())
+    report.repaired = true;
+    report.violations = rules.validate(&response.text, context);
+    Ok(response.text)
 }
 
+/// Starts refining `draft`, keeping the context of the last generation as history.
 #[tauri::command]
-fn load_file_cmd(file_path: String) -> Result<String, String> {
-    read_file_content(Some(&file_path), "User Notes")
-        .map_err(|e| e.to_string())
-        .and_then(|opt| opt.ok_or_else(|| "File not found or empty".to_string()))
+fn start_chat_cmd(
+    state: tauri::State<'_, GenerationState>,
+    chat: tauri::State<'_, ChatState>,
+    model: String,
+    system_prompt: Option<String>,
+    ollama_options: Option<OllamaOptions>,
+    draft: String,
+) -> Result<(), String> {
+    let context = state
+        .last_context
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| "Generate release notes before refining them".to_string())?;
+
+    let session = ChatSession::new(
+        &model,
+        "http://localhost:11434",
+        system_prompt.as_deref(),
+        &context,
+        OllamaOptions {
+            format: None,
+            ..ollama_options.unwrap_or_default()
+        },
+    )
+    .with_draft(&draft);
+    *chat.session.lock().unwrap() = Some(session);
+    Ok(())
 }
 
+/// Sends a follow-up instruction, streaming the revised draft as `ai-token` events.
 #[tauri::command]
-fn save_file_cmd(file_path: String, content: String) -> Result<(), String> {
-    fs::write(file_path, content).map_err(|e| e.to_string())
+async fn continue_chat_cmd(
+    window: Window,
+    state: tauri::State<'_, GenerationState>,
+    chat: tauri::State<'_, ChatState>,
+    message: String,
+) -> Result<GenerationResult, String> {
+    // Take the session out so the lock is not held across the request
+    let mut session = chat
+        .session
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| "No refinement session in progress".to_string())?;
+
+    let cancel = CancellationToken::new();
+    *state.cancel.lock().unwrap() = Some(cancel.clone());
+
+    let callback = |token: &str| {
+        let _ = window.emit("ai-token", token);
+    };
+    let result = session.send(Some(&message), callback, Some(&cancel)).await;
+    state.cancel.lock().unwrap().take();
+
+    let output = session.latest_draft().unwrap_or_default().to_string();
+    *chat.session.lock().unwrap() = Some(session);
+    let response = result.map_err(|e| e.to_string())?;
+
+    Ok(GenerationResult {
+        output,
+        cached: false,
+        cancelled: cancel.is_cancelled(),
+        report: GenerationReport {
+            ollama: response.stats,
+            ..Default::default()
+        },
+    })
 }
 
-#[tauri::command]
-fn get_repo_refs_cmd(repo_path: String) -> Result<Vec<String>, String> {
-    list_git_refs(Some(Path::new(&repo_path))).map_err(|e| e.to_string())
+#[derive(Serialize)]
+struct TranslationResult {
+    output: String,
+    cancelled: bool,
+    check: TranslationCheck,
 }
 
+/// Translates the notes into `locale`, keeping `glossary` terms (one per
+/// line), issue keys and code untranslated.
 #[tauri::command]
-async fn get_ollama_models_cmd() -> Result<Vec<String>, String> {
-    let url = "http://localhost:11434";
-    list_ollama_models(url).await.map_err(|e| e.to_string())
+async fn translate_notes_cmd(
+    state: tauri::State<'_, GenerationState>,
+    model: String,
+    markdown: String,
+    locale: String,
+    glossary: Option<String>,
+    ollama_options: Option<OllamaOptions>,
+) -> Result<TranslationResult, String> {
+    let glossary = Glossary::parse(&glossary.unwrap_or_default());
+    let cancel = CancellationToken::new();
+    *state.cancel.lock().unwrap() = Some(cancel.clone());
+    let result = translate::translate_notes(
+        &model,
+        "http://localhost:11434/api/generate",
+        &markdown,
+        &locale,
+        &glossary,
+        &ollama_options.unwrap_or_default(),
+        Some(&cancel),
+    )
+    .await;
+    state.cancel.lock().unwrap().take();
+
+    Ok(match result.map_err(|e| e.to_string())? {
+        Some(translation) => TranslationResult {
+            output: translation.text,
+            cancelled: false,
+            check: translation.check,
+        },
+        None => TranslationResult {
+            output: String::new(),
+            cancelled: true,
+            check: TranslationCheck {
+                locale,
+                ..Default::default()
+            },
+        },
+    })
 }
 
+/// Creates or updates the forge release for `tag`. A dry run only reports
+/// what would happen. The attached context is the last one generated.
 #[tauri::command]
-async fn generate_preview_cmd(
+#[allow(clippy::too_many_arguments)]
+async fn publish_release_cmd(
+    state: tauri::State<'_, GenerationState>,
     repo_path: String,
-    start: String,
-    end: String,
+    tag: String,
     notes: String,
-    jira_url: Option<String>,
-    jira_pat: Option<String>,
-) -> Result<String, String> {
-    let jira_config = if let (Some(url), Some(pat)) = (jira_url, jira_pat) {
-        if !url.is_empty() && !pat.is_empty() {
-            Some(JiraConfig { url, pat })
-        } else {
-            None
-        }
+    forge: String,
+    forge_repo: Option<String>,
+    forge_url: Option<String>,
+    attach_context: bool,
+    dry_run: bool,
+) -> Result<PublishOutcome, String> {
+    let kind = ForgeKind::from_str(&forge).map_err(|e| e.to_string())?;
+    let cwd = Path::new(&repo_path);
+    if !publish::tag_exists(&tag, Some(cwd)) {
+        return Err(format!("Releases need a tag, and '{}' is not one", tag));
+    }
+    let repo = match forge_repo.filter(|r| !r.trim().is_empty()) {
+        Some(repo) => repo,
+        None => publish::detect_repo(Some(cwd)).map_err(|e| e.to_string())?,
+    };
+    let config = ForgeConfig {
+        kind,
+        api_url: forge_url
+            .filter(|u| !u.trim().is_empty())
+            .unwrap_or_else(|| kind.default_api_url().to_string()),
+        repo,
+        token: forge_token(kind).map(|(token, _)| token),
+    };
+    let attachment = if attach_context {
+        let context = state.last_context.lock().unwrap().clone();
+        Some(Attachment {
+            file_name: "release_context.md".to_string(),
+            content: context
+                .ok_or("Generate the notes before attaching their context")?
+                .into_bytes(),
+        })
     } else {
         None
     };
+    let draft = ReleaseDraft {
+        name: publish::release_title(&notes, &tag),
+        tag,
+        body: notes,
+        attachment,
+    };
+    publish::publish_release(&config, &draft, dry_run)
+        .await
+        .map_err(|e| e.to_string())
+}
 
-    generate_context(
-        &start,
-        &end,
-        Some(notes),
-        Some(Path::new(&repo_path)),
-        jira_config,
+#[derive(Serialize)]
+struct ConfluenceResult {
+    outcome: PageOutcome,
+    /// The page body in Confluence storage format.
+    storage: String,
+}
+
+/// Creates or updates the Confluence page for the notes, using the Jira
+/// credentials. A dry run returns the storage-format body without writing.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn publish_confluence_cmd(
+    notes: String,
+    start: String,
+    end: String,
+    jira_url: Option<String>,
+    confluence_url: Option<String>,
+    space: String,
+    parent_id: String,
+    title: Option<String>,
+    dry_run: bool,
+) -> Result<ConfluenceResult, String> {
+    let config = build_jira_config(jira_url, None)
+        .ok_or("Publishing to Confluence uses the Jira URL and token; set them in Settings")?;
+    let target = PageTarget {
+        url: confluence_url
+            .filter(|u| !u.trim().is_empty())
+            .unwrap_or_else(|| config.url.clone()),
+        space,
+        parent_id,
+        title: title
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or_else(|| publish::release_title(&notes, &end)),
+    };
+    let storage = render::to_confluence_storage(&confluence::strip_title(&notes));
+    let outcome = confluence::publish_page(
+        &config,
+        &target,
+        &storage,
+        &confluence::version_comment(&start, &end),
+        dry_run,
     )
     .await
-    .map_err(|e| e.to_string())
+    .map_err(|e| e.to_string())?;
+    Ok(ConfluenceResult { outcome, storage })
 }
 
+/// Writes the release back to Jira: the fix version's description and,
+/// optionally, a comment and label on each issue. A dry run only plans.
 #[tauri::command]
 #[allow(clippy::too_many_arguments)]
-async fn generate_ai_cmd(
-    window: Window,
+async fn jira_write_cmd(
+    jira_url: Option<String>,
+    issue_keys: Vec<String>,
+    version: String,
+    notes: String,
+    comment: bool,
+    label: Option<String>,
+    link: Option<String>,
+    dry_run: bool,
+) -> Result<Vec<JiraWriteOutcome>, String> {
+    let config =
+        build_jira_config(jira_url, None).ok_or("Set the Jira URL and token in Settings first")?;
+    let write_back = WriteBack {
+        comment: comment.then(|| jira::release_comment(&version, link.as_deref())),
+        description: jira::version_description(&notes),
+        label: label.filter(|l| !l.trim().is_empty()),
+        version,
+    };
+    let writes = jira::plan_writes(&issue_keys, &write_back);
+    Ok(jira::apply_writes(&config, writes, dry_run).await)
+}
+
+#[derive(Serialize)]
+struct WebhookResult {
+    outcome: NotifyOutcome,
+    payload: String,
+}
+
+/// Posts the notes' executive summary to the chat webhook stored in the
+/// keyring. A dry run only renders the payload.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn send_webhook_cmd(
     repo_path: String,
+    format: Option<String>,
+    template: Option<String>,
+    notes: String,
     start: String,
     end: String,
-    notes: String,
-    model: String,
-    system_prompt: Option<String>,
-    jira_url: Option<String>,
-    jira_pat: Option<String>,
-) -> Result<String, String> {
-    let jira_config = if let (Some(url), Some(pat)) = (jira_url, jira_pat) {
-        if !url.is_empty() && !pat.is_empty() {
-            Some(JiraConfig { url, pat })
-        } else {
-            None
-        }
-    } else {
-        None
+    link: Option<String>,
+    dry_run: bool,
+) -> Result<WebhookResult, String> {
+    let url = keyring_secret(KEYRING_WEBHOOK_URL)
+        .ok_or_else(|| "Set a webhook URL under Settings > Chat Notification first".to_string())?;
+    let format = match format.filter(|f| !f.is_empty()) {
+        Some(format) => WebhookFormat::from_str(&format).map_err(|e| e.to_string())?,
+        None => WebhookFormat::detect(&url),
     };
+    let announcement = Announcement {
+        version: PromptVars::from_repo(&start, &end, Some(Path::new(&repo_path))).version,
+        url: link,
+        ..Announcement::from_notes(&notes, &start, &end)
+    };
+    let template = template.filter(|t| !t.trim().is_empty());
+    let payload = notify::render_payload(format, template.as_deref(), &announcement)
+        .map_err(|e| format!("{:#}", e))?;
+    let outcome = notify::send(&url, format, &payload, dry_run)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(WebhookResult { outcome, payload })
+}
 
-    let context = generate_context(
-        &start,
+/// Stores the notes in the end tag's message or as git notes on the end
+/// commit. A dry run only reports what would change. An existing tag
+/// message is only replaced with `overwrite_tag`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn store_notes_cmd(
+    repo_path: String,
+    end: String,
+    notes: String,
+    store: String,
+    notes_ref: Option<String>,
+    overwrite_tag: Option<bool>,
+    dry_run: bool,
+) -> Result<StoreOutcome, String> {
+    let store = NotesStore::from_str(&store).map_err(|e| e.to_string())?;
+    let notes_ref = notes_ref
+        .filter(|r| !r.trim().is_empty())
+        .unwrap_or_else(|| git_notes::DEFAULT_NOTES_REF.to_string());
+    git_notes::store_notes(
+        store,
         &end,
-        Some(notes),
+        &notes_ref,
+        &notes,
+        overwrite_tag.unwrap_or(false),
+        dry_run,
         Some(Path::new(&repo_path)),
-        jira_config,
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .map_err(|e| format!("{:#}", e))
+}
 
-    let url = "http://localhost:11434/api/generate";
+/// Fills `{{product_name}}`, `{{version}}` etc. in a prompt template. Values
+/// not given in `vars` are detected from the repository where possible.
+#[tauri::command]
+fn render_prompt_cmd(
+    repo_path: String,
+    start: String,
+    end: String,
+    template: String,
+    vars: Option<PromptVars>,
+) -> Result<String, String> {
+    let cwd = Some(Path::new(&repo_path));
+    let vars = PromptVars::from_repo(&start, &end, cwd).merge(vars.unwrap_or_default());
+    prompt::render_prompt(&template, &vars).map_err(|e| format!("{:#}", e))
+}
 
-    // Create a callback that emits events to the window
-    let callback = move |token: &str| {
-        let _ = window.emit("ai-token", token);
-    };
+/// Converts Markdown notes into another format for export.
+#[tauri::command]
+fn render_notes_cmd(markdown: String, format: String) -> Result<String, String> {
+    let format = NotesFormat::from_str(&format).map_err(|e| e.to_string())?;
+    render::render_markdown(&markdown, format, None).map_err(|e| e.to_string())
+}
 
-    call_ollama(
-        &model,
-        url,
-        &context,
-        system_prompt.as_ref(),
-        Some(callback),
-    )
-    .await
-    .map_err(|e| e.to_string())
+/// The built-in context layout, shown as a starting point in Settings.
+#[tauri::command]
+fn default_context_template_cmd() -> String {
+    layout::DEFAULT_CONTEXT_TEMPLATE.to_string()
+}
+
+#[derive(Serialize)]
+struct ReviewResult {
+    report: GroundingReport,
+    annotated: String,
+}
+
+/// Traces each bullet of `output` back to the commits, issues and adhoc notes
+/// of the last generation.
+#[tauri::command]
+fn review_notes_cmd(
+    state: tauri::State<'_, GenerationState>,
+    output: String,
+    notes: Option<String>,
+) -> Result<ReviewResult, String> {
+    if state.last_context.lock().unwrap().is_none() {
+        return Err("Generate release notes before reviewing them".to_string());
+    }
+    let context_sources = state.last_sources.lock().unwrap().clone();
+    let sources = grounding::collect_sources(&context_sources, notes.as_deref());
+    let report =
+        grounding::check_grounding(&output, &sources, grounding::DEFAULT_SIMILARITY_THRESHOLD);
+    let annotated = report.annotate(&output);
+    Ok(ReviewResult { report, annotated })
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -290,6 +1134,8 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_store::Builder::default().build())
+        .manage(GenerationState::default())
+        .manage(ChatState::default())
         .setup(|app| {
             let handle = app.handle();
 
@@ -371,6 +1217,27 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             generate_preview_cmd,
             generate_ai_cmd,
+            generate_audiences_cmd,
+            cancel_generation_cmd,
+            start_chat_cmd,
+            continue_chat_cmd,
+            review_notes_cmd,
+            render_prompt_cmd,
+            default_context_template_cmd,
+            render_notes_cmd,
+            translate_notes_cmd,
+            publish_release_cmd,
+            publish_confluence_cmd,
+            jira_write_cmd,
+            send_webhook_cmd,
+            store_notes_cmd,
+            save_jira_pat_cmd,
+            jira_pat_source_cmd,
+            save_forge_token_cmd,
+            forge_token_source_cmd,
+            save_webhook_url_cmd,
+            webhook_host_cmd,
+            clear_jira_cache_cmd,
             load_file_cmd,
             save_file_cmd,
             get_repo_refs_cmd,
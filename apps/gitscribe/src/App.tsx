@@ -1,5 +1,5 @@
 import { useState, useEffect } from "react";
-import { FolderOpen, Play, Loader2, FileText, Upload, Copy, Bot, RefreshCw, WifiOff, Settings, Save, Check } from "lucide-react";
+import { FolderOpen, Play, Loader2, FileText, Upload, Copy, Bot, RefreshCw, WifiOff, Settings, Save, Check, Database, Square, Send, ShieldCheck, ChevronDown, Rocket, Bell, Tag } from "lucide-react";
 import { invoke } from "@tauri-apps/api/core";
 import { open, save } from "@tauri-apps/plugin-dialog";
 import { listen } from "@tauri-apps/api/event";
@@ -7,6 +7,120 @@ import { LazyStore } from "@tauri-apps/plugin-store";
 import { SettingsModal } from "./SettingsModal";
 import "./App.css";
 
+interface GenerationReport {
+  start: string;
+  end: string;
+  audience: string | null;
+  model: string | null;
+  context: {
+    commit_count: number;
+    diff_files: number;
+    diff_bytes: number;
+    context_bytes: number;
+    jira: { fetched: number; cached: number; stale: number; failed: number } | null;
+    issue_keys: string[];
+    redactions: { detector: string; count: number; samples: string[] }[];
+    style_examples: string[];
+  };
+  system_prompt_bytes: number;
+  estimated_prompt_tokens: number;
+  cache_hit: boolean;
+  cancelled: boolean;
+  ollama: { prompt_eval_count: number | null; eval_count: number | null; eval_duration: number | null } | null;
+  context_ms: number;
+  generation_ms: number;
+  violations: { kind: string; message: string }[];
+  repaired: boolean;
+}
+
+type NotesFormat = "markdown" | "html" | "asciidoc" | "rst" | "text" | "json";
+
+interface TemplateInfo {
+  name: string;
+  builtin: boolean;
+}
+
+interface GenerationResult {
+  output: string;
+  cached: boolean;
+  cancelled: boolean;
+  report: GenerationReport;
+}
+
+// One tab of a multi-audience run
+interface AudienceOutput {
+  name: string;
+  prompt: string;
+  result: GenerationResult;
+}
+
+interface TranslationResult {
+  output: string;
+  cancelled: boolean;
+  check: { locale: string; problems: string[] };
+}
+
+interface PublishOutcome {
+  action: "create" | "update";
+  dry_run: boolean;
+  repo: string;
+  tag: string;
+  url: string | null;
+  attachment: string | null;
+}
+
+interface ConfluenceResult {
+  outcome: { action: "create" | "update"; dry_run: boolean; space: string; title: string; version: number; url: string | null };
+  storage: string;
+}
+
+interface JiraWriteOutcome {
+  kind: "version_description" | "comment" | "label";
+  project?: string;
+  version?: string;
+  key?: string;
+  label?: string;
+  dry_run: boolean;
+  applied: boolean;
+  skipped: string | null;
+  error: string | null;
+}
+
+const describeJiraWrite = (w: JiraWriteOutcome) =>
+  (w.kind === "version_description" ? `Set the description of fix version ${w.version} in ${w.project}`
+  : w.kind === "comment" ? `Comment on ${w.key}`
+  : `Add label ${w.label} to ${w.key}`)
+  + (w.skipped ? ` (skipped: ${w.skipped})` : "");
+
+interface WebhookResult {
+  outcome: { format: "slack" | "teams" | "generic"; dry_run: boolean; host: string };
+  payload: string;
+}
+
+interface StoreOutcome {
+  store: "tag" | "git-notes";
+  dry_run: boolean;
+  rev: string;
+  notes_ref: string | null;
+  replaced: boolean;
+}
+
+const describeStore = (o: StoreOutcome) =>
+  o.store === "tag" ? `in the message of tag ${o.rev}` : `on ${o.rev} in git notes (${o.notes_ref})`;
+
+interface ReviewResult {
+  report: { bullets: { line: number; text: string; evidence: unknown[] }[] };
+  annotated: string;
+}
+
+// Formats the notes can be exported to from Markdown
+const EXPORT_FORMATS = [
+  { format: "html", label: "HTML", extension: "html" },
+  { format: "asciidoc", label: "AsciiDoc", extension: "adoc" },
+  { format: "rst", label: "reStructuredText", extension: "rst" },
+  { format: "text", label: "Plain Text", extension: "txt" },
+];
+
 // Initialize store outside component to avoid re-creation
 const store = new LazyStore("settings.dat");
 
@@ -15,6 +129,9 @@ function App() {
   const [startRef, setStartRef] = useState("");
   const [endRef, setEndRef] = useState("");
   const [refs, setRefs] = useState<string[]>([]);
+  const [pathFilter, setPathFilter] = useState("");
+  const [releaseVersion, setReleaseVersion] = useState("");
+  const [releaseDate, setReleaseDate] = useState("");
   const [notes, setNotes] = useState("");
   const [activeTab, setActiveTab] = useState<"strategy" | "preview" | "result">("strategy");
   const [isGenerating, setIsGenerating] = useState(false);
@@ -27,11 +144,30 @@ function App() {
   const [isSettingsOpen, setIsSettingsOpen] = useState(false);
   const [isJiraEnabled, setIsJiraEnabled] = useState(false);
   const [copySuccess, setCopySuccess] = useState(false);
+  const [isExportOpen, setIsExportOpen] = useState(false);
+  const [isPublishing, setIsPublishing] = useState(false);
+  // Release or Confluence page published for the current notes, linked from Jira comments and chat
+  const [publishedUrl, setPublishedUrl] = useState<string | null>(null);
+  const [locales, setLocales] = useState<string[]>([]);
+  const [activeLocale, setActiveLocale] = useState("");
+  const [translations, setTranslations] = useState<Record<string, TranslationResult>>({});
+  const [usedCache, setUsedCache] = useState(false);
+  const [report, setReport] = useState<GenerationReport | null>(null);
+  const [isStructured, setIsStructured] = useState(false);
+  const [outputFormat, setOutputFormat] = useState<NotesFormat>("markdown");
+  const [autoRepair, setAutoRepair] = useState(false);
+  const [review, setReview] = useState<ReviewResult | null>(null);
+  const [refineInput, setRefineInput] = useState("");
+  const [isChatActive, setIsChatActive] = useState(false);
   
   // Template State
-  const [templates, setTemplates] = useState<string[]>([]);
+  const [templates, setTemplates] = useState<TemplateInfo[]>([]);
   const [selectedTemplate, setSelectedTemplate] = useState<string>("");
   const [systemPrompt, setSystemPrompt] = useState<string>("");
+  const [renderedPrompt, setRenderedPrompt] = useState<string>("");
+  const [extraAudiences, setExtraAudiences] = useState<string[]>([]);
+  const [audienceOutputs, setAudienceOutputs] = useState<AudienceOutput[]>([]);
+  const [activeAudience, setActiveAudience] = useState<string>("");
 
   // Check for Tauri environment
   // @ts-ignore
@@ -54,7 +190,7 @@ function App() {
         // MOCK DATA FOR BROWSER/TESTS
         setOllamaModels(["llama3", "mistral"]);
         setOllamaStatus("ok");
-        setTemplates(["default.md"]);
+        setTemplates([{ name: "end-user", builtin: true }]);
         return;
     }
 
@@ -71,11 +207,45 @@ function App() {
         const savedModel = await store.get("model");
         if (savedModel) setModel(savedModel as string);
 
-        const savedTemplate = await store.get("selectedTemplate");
-        if (savedTemplate) setSelectedTemplate(savedTemplate as string);
-
         const savedJira = await store.get("isJiraEnabled");
         if (savedJira !== null) setIsJiraEnabled(savedJira as boolean);
+
+        // Earlier versions kept the Jira token in plain text in the store
+        const legacyPat = await store.get<string>("jira_pat");
+        if (legacyPat) {
+            try {
+                await invoke("save_jira_pat_cmd", { pat: legacyPat });
+                await store.delete("jira_pat");
+                await store.save();
+            } catch (err) {
+                console.error("Failed to move the Jira token to the keyring:", err);
+            }
+        }
+
+        const legacyForgeToken = await store.get<string>("forge_token");
+        const legacyForge = await store.get<string>("forge");
+        if (legacyForgeToken && legacyForge) {
+            try {
+                await invoke("save_forge_token_cmd", { forge: legacyForge, token: legacyForgeToken });
+                await store.delete("forge_token");
+                await store.save();
+            } catch (err) {
+                console.error("Failed to move the access token to the keyring:", err);
+            }
+        }
+
+        const legacyWebhookUrl = await store.get<string>("webhook_url");
+        if (legacyWebhookUrl) {
+            try {
+                await invoke("save_webhook_url_cmd", { url: legacyWebhookUrl });
+                await store.delete("webhook_url");
+                await store.save();
+            } catch (err) {
+                console.error("Failed to move the webhook URL to the keyring:", err);
+            }
+        }
+
+        loadLocales();
       } catch (e) {
         console.warn("Failed to load settings (running in browser?):", e);
       }
@@ -88,27 +258,69 @@ function App() {
   // Save settings on change
   useEffect(() => { if (isTauri) store.set("repoPath", repoPath); }, [repoPath, isTauri]);
   useEffect(() => { if (isTauri) store.set("model", model); }, [model, isTauri]);
-  useEffect(() => { if (isTauri) store.set("selectedTemplate", selectedTemplate); }, [selectedTemplate, isTauri]);
+  useEffect(() => { if (isTauri && selectedTemplate) store.set("selectedTemplate", selectedTemplate); }, [selectedTemplate, isTauri]);
   useEffect(() => { if (isTauri) store.set("isJiraEnabled", isJiraEnabled); }, [isJiraEnabled, isTauri]);
 
   // When template changes, load content
   useEffect(() => {
     if (isTauri && selectedTemplate) {
-        invoke("load_template_cmd", { filename: selectedTemplate })
+        invoke("load_template_cmd", { name: selectedTemplate })
             .then(content => setSystemPrompt(content as string))
             .catch(e => console.error("Failed to load template:", e));
     }
   }, [selectedTemplate, isTauri]);
 
+  const loadLocales = async () => {
+      const saved = await store.get<string>("locales");
+      setLocales((saved || "").split(",").map(l => l.trim()).filter(Boolean));
+  };
+
+  // Translations belong to the notes they were made from
+  useEffect(() => {
+    setTranslations({});
+    setActiveLocale("");
+    setPublishedUrl(null);
+  }, [aiContent]);
+
+  const shownContent = activeLocale && translations[activeLocale] ? translations[activeLocale].output : aiContent;
+
+  const selectLocale = async (locale: string) => {
+    setReview(null);
+    if (!locale || translations[locale]) {
+      setActiveLocale(locale);
+      return;
+    }
+    setIsGenerating(true);
+    try {
+      const result = await invoke<TranslationResult>("translate_notes_cmd", {
+        model,
+        markdown: aiContent,
+        locale,
+        glossary: (await store.get<string>("glossary")) || undefined,
+        ollamaOptions: (await store.get("ollama_options")) || undefined
+      });
+      if (!result.cancelled) {
+        setTranslations(prev => ({ ...prev, [locale]: result }));
+        setActiveLocale(locale);
+      }
+    } catch (e) {
+      alert(`Translation failed: ${e}`);
+    } finally {
+      setIsGenerating(false);
+    }
+  };
+
   const loadTemplates = async () => {
       if (!isTauri) return;
       try {
-          const list = await invoke("list_templates_cmd");
-          const tmpls = list as string[];
+          const tmpls = await invoke<TemplateInfo[]>("list_templates_cmd");
           setTemplates(tmpls);
-          // Select default if available, or first one
-          if (tmpls.includes("default.md")) setSelectedTemplate("default.md");
-          else if (tmpls.length > 0) setSelectedTemplate(tmpls[0]);
+          // Keep the last selection if it still exists, else fall back to the default
+          const saved = await store.get<string>("selectedTemplate");
+          const names = tmpls.map(t => t.name);
+          if (saved && names.includes(saved)) setSelectedTemplate(saved);
+          else if (names.includes("end-user")) setSelectedTemplate("end-user");
+          else if (names.length > 0) setSelectedTemplate(names[0]);
       } catch (e) {
           console.error("Failed to load templates:", e);
       }
@@ -166,20 +378,49 @@ function App() {
     }
   };
 
+  // Comma or newline separated pathspecs; a leading "!" excludes the path
+  const parsePaths = () => pathFilter.split(/[,\n]/).map(p => p.trim()).filter(p => p.length > 0);
+
+  // Notes ref to read the start ref's stored notes from, if enabled
+  const storedNotesRef = async () =>
+    (await store.get<boolean>("use_stored_notes"))
+      ? (await store.get<string>("notes_ref")) || "release-notes"
+      : undefined;
+
+  // Redaction settings for the context; redaction is on unless turned off
+  const redactionOptions = async () => ({
+    redact: (await store.get<boolean>("redact")) ?? true,
+    redactPatterns: ((await store.get<string>("redact_patterns")) || "")
+      .split("\n").map(p => p.trim()).filter(p => p.length > 0),
+  });
+
+  // Style example defaults; the selected templates can override them
+  const styleSettings = async () => ({
+    source: (await store.get<string>("style_examples")) || null,
+    count: (await store.get<number>("style_count")) ?? null,
+    max_chars: (await store.get<number>("style_max_chars")) ?? null,
+  });
+
   const loadPreview = async () => {
     if (!isTauri) return;
     if (!repoPath || !startRef || !endRef) return;
     try {
-      let jiraUrl, jiraPat;
+      let jiraUrl, jiraCacheTtl;
       if (isJiraEnabled) {
           jiraUrl = await store.get("jira_url");
-          jiraPat = await store.get("jira_pat");
+          jiraCacheTtl = await store.get("jira_cache_ttl");
       }
 
       const res = await invoke("generate_preview_cmd", {
         repoPath, start: startRef, end: endRef, notes,
+        paths: parsePaths(),
+        contextTemplate: (await store.get("context_template")) || undefined,
+        storedNotesRef: await storedNotesRef(),
+        ...(await redactionOptions()),
+        style: await styleSettings(),
+        template: systemPrompt || undefined,
         jiraUrl: jiraUrl || undefined,
-        jiraPat: jiraPat || undefined
+        jiraCacheTtl: jiraCacheTtl ?? undefined
       });
       setPreviewContent(res as string);
     } catch (e) {
@@ -192,7 +433,7 @@ function App() {
       const timeout = setTimeout(loadPreview, 500); // Debounce
       return () => clearTimeout(timeout);
     }
-  }, [repoPath, startRef, endRef, notes, activeTab]);
+  }, [repoPath, startRef, endRef, notes, pathFilter, systemPrompt, activeTab]);
 
   // System Event Listeners
   useEffect(() => {
@@ -216,15 +457,21 @@ function App() {
     const unlisten = listen<string>("ai-token", (event) => {
         setAiContent(prev => prev + event.payload);
     });
+    // Multi-audience runs announce each audience before streaming its notes
+    const unlistenAudience = listen<string>("ai-audience", (event) => {
+        setActiveAudience(event.payload);
+        setAiContent("");
+    });
     return () => {
         unlisten.then(f => f());
+        unlistenAudience.then(f => f());
     };
   }, [isTauri]);
 
   const handleCopy = async () => {
-    if (!aiContent) return;
+    if (!shownContent) return;
     try {
-      await navigator.clipboard.writeText(aiContent);
+      await navigator.clipboard.writeText(shownContent);
       setCopySuccess(true);
       setTimeout(() => setCopySuccess(false), 2000);
     } catch (err) {
@@ -235,7 +482,8 @@ function App() {
   const handleSave = async () => {
     if (!aiContent) return;
     try {
-      const suggestedName = mode === "auto" ? `release-notes-${endRef || "draft"}.md` : `context-${endRef || "draft"}.txt`;
+      const audienceSuffix = (activeAudience ? `-${activeAudience}` : "") + (activeLocale ? `.${activeLocale}` : "");
+      const suggestedName = mode === "auto" ? `release-notes-${endRef || "draft"}${audienceSuffix}.md` : `context-${endRef || "draft"}.txt`;
       const filePath = await save({
         defaultPath: suggestedName,
         filters: [{
@@ -245,7 +493,7 @@ function App() {
       });
 
       if (filePath) {
-        await invoke("save_file_cmd", { filePath, content: aiContent });
+        await invoke("save_file_cmd", { filePath, content: shownContent });
       }
     } catch (err) {
       console.error("Failed to save:", err);
@@ -253,28 +501,271 @@ function App() {
     }
   };
 
-  const handleGenerate = async () => {
+  const handleExport = async (target: typeof EXPORT_FORMATS[number]) => {
+    setIsExportOpen(false);
+    if (!aiContent) return;
+    try {
+      const audienceSuffix = (activeAudience ? `-${activeAudience}` : "") + (activeLocale ? `.${activeLocale}` : "");
+      const filePath = await save({
+        defaultPath: `release-notes-${endRef || "draft"}${audienceSuffix}.${target.extension}`,
+        filters: [{ name: target.label, extensions: [target.extension] }]
+      });
+      if (filePath) {
+        const content = await invoke<string>("render_notes_cmd", { markdown: shownContent, format: target.format });
+        await invoke("save_file_cmd", { filePath, content });
+      }
+    } catch (err) {
+      console.error("Failed to export:", err);
+      alert(`Failed to export file: ${err}`);
+    }
+  };
+
+  const handlePublish = async () => {
+    if (!aiContent) return;
+    const forge = await store.get<string>("forge");
+    if (!forge) {
+      alert("Choose GitHub or GitLab under Settings > Release Publishing first.");
+      return;
+    }
+    const args = {
+      repoPath, tag: endRef, notes: shownContent, forge,
+      forgeRepo: (await store.get<string>("forge_repo")) || undefined,
+      forgeUrl: (await store.get<string>("forge_url")) || undefined,
+      attachContext: (await store.get<boolean>("attach_context")) ?? false,
+    };
+    setIsPublishing(true);
+    try {
+      // Look the release up first so the user sees what will change
+      const plan = await invoke<PublishOutcome>("publish_release_cmd", { ...args, dryRun: true });
+      const verb = plan.action === "update" ? "Update the existing" : "Create a";
+      const attachment = plan.attachment ? ` and attach ${plan.attachment}` : "";
+      if (!confirm(`${verb} release ${plan.tag} on ${plan.repo}${attachment}?`)) return;
+      const outcome = await invoke<PublishOutcome>("publish_release_cmd", { ...args, dryRun: false });
+      setPublishedUrl(outcome.url);
+      alert(`Release ${outcome.tag} ${outcome.action === "update" ? "updated" : "created"}${outcome.url ? `: ${outcome.url}` : "."}`);
+    } catch (err) {
+      console.error("Failed to publish:", err);
+      alert(`Failed to publish release: ${err}`);
+    } finally {
+      setIsPublishing(false);
+    }
+  };
+
+  const handleConfluence = async () => {
+    if (!aiContent) return;
+    const space = await store.get<string>("confluence_space");
+    const parentId = await store.get<string>("confluence_parent");
+    if (!space || !parentId) {
+      alert("Set the Confluence space and parent page under Settings > Confluence Publishing first.");
+      return;
+    }
+    const args = {
+      notes: shownContent, start: startRef, end: endRef,
+      jiraUrl: (await store.get<string>("jira_url")) || undefined,
+      confluenceUrl: (await store.get<string>("confluence_url")) || undefined,
+      space, parentId,
+    };
+    setIsPublishing(true);
+    try {
+      const plan = await invoke<ConfluenceResult>("publish_confluence_cmd", { ...args, dryRun: true });
+      const verb = plan.outcome.action === "update" ? `Update the Confluence page (version ${plan.outcome.version})` : "Create the Confluence page";
+      if (!confirm(`${verb} "${plan.outcome.title}" in ${plan.outcome.space}?`)) return;
+      const result = await invoke<ConfluenceResult>("publish_confluence_cmd", { ...args, dryRun: false });
+      setPublishedUrl(prev => prev ?? result.outcome.url);
+      alert(`Confluence page ${result.outcome.action === "update" ? "updated" : "created"}${result.outcome.url ? `: ${result.outcome.url}` : "."}`);
+    } catch (err) {
+      console.error("Failed to publish to Confluence:", err);
+      alert(`Failed to publish to Confluence: ${err}`);
+    } finally {
+      setIsPublishing(false);
+    }
+  };
+
+  const handleJiraWrite = async () => {
+    if (!aiContent || !report?.context.issue_keys.length) return;
+    const version = prompt("Fix version to update in Jira:", endRef);
+    if (!version) return;
+    const args = {
+      jiraUrl: (await store.get<string>("jira_url")) || undefined,
+      issueKeys: report.context.issue_keys,
+      version, notes: shownContent,
+      comment: (await store.get<boolean>("jira_write_comment")) ?? false,
+      label: (await store.get<string>("jira_write_label")) || undefined,
+      link: publishedUrl || undefined,
+    };
+    setIsPublishing(true);
+    try {
+      const plan = await invoke<JiraWriteOutcome[]>("jira_write_cmd", { ...args, dryRun: true });
+      const planFailed = plan.filter(o => o.error);
+      if (planFailed.length > 0) {
+        alert(`Could not check the fix version in Jira:\n\n${planFailed.map(o => `${describeJiraWrite(o)}: ${o.error}`).join("\n")}`);
+        return;
+      }
+      if (!confirm(`Make these changes in Jira?\n\n${plan.map(describeJiraWrite).join("\n")}`)) return;
+      const outcomes = await invoke<JiraWriteOutcome[]>("jira_write_cmd", { ...args, dryRun: false });
+      const failed = outcomes.filter(o => o.error);
+      alert(failed.length === 0
+        ? `Applied ${outcomes.filter(o => o.applied).length} change(s) in Jira.`
+        : `${failed.length} of ${outcomes.length} change(s) failed:\n\n${failed.map(o => `${describeJiraWrite(o)}: ${o.error}`).join("\n")}`);
+    } catch (err) {
+      console.error("Failed to write to Jira:", err);
+      alert(`Failed to write to Jira: ${err}`);
+    } finally {
+      setIsPublishing(false);
+    }
+  };
+
+  const handleNotify = async () => {
+    if (!aiContent) return;
+    if (!(await invoke<string | null>("webhook_host_cmd"))) {
+      alert("Set a webhook URL under Settings > Chat Notification first.");
+      return;
+    }
+    const args = {
+      repoPath, notes: shownContent, start: startRef, end: endRef,
+      format: (await store.get<string>("webhook_format")) || undefined,
+      template: (await store.get<string>("webhook_template")) || undefined,
+      link: publishedUrl || undefined,
+    };
+    setIsPublishing(true);
+    try {
+      const plan = await invoke<WebhookResult>("send_webhook_cmd", { ...args, dryRun: true });
+      if (!confirm(`Post this ${plan.outcome.format} message to ${plan.outcome.host}?\n\n${plan.payload}`)) return;
+      const result = await invoke<WebhookResult>("send_webhook_cmd", { ...args, dryRun: false });
+      alert(`Summary posted to ${result.outcome.host}.`);
+    } catch (err) {
+      console.error("Failed to post to the webhook:", err);
+      alert(`Failed to post to the webhook: ${err}`);
+    } finally {
+      setIsPublishing(false);
+    }
+  };
+
+  const handleStoreNotes = async () => {
+    if (!aiContent) return;
+    const args = {
+      repoPath, end: endRef, notes: shownContent,
+      store: (await store.get<string>("notes_store")) || "git-notes",
+      notesRef: (await store.get<string>("notes_ref")) || undefined,
+    };
+    setIsPublishing(true);
+    try {
+      // Plan with overwriting allowed; the confirmation below is the explicit opt-in
+      const plan = await invoke<StoreOutcome>("store_notes_cmd", { ...args, overwriteTag: true, dryRun: true });
+      if (!confirm(`${plan.replaced ? "Replace" : "Store"} the notes ${describeStore(plan)}?`)) return;
+      const result = await invoke<StoreOutcome>("store_notes_cmd", { ...args, overwriteTag: plan.replaced, dryRun: false });
+      alert(`Notes stored ${describeStore(result)}. Push them to share them with the team.`);
+    } catch (err) {
+      console.error("Failed to store the notes:", err);
+      alert(`Failed to store the notes: ${err}`);
+    } finally {
+      setIsPublishing(false);
+    }
+  };
+
+  // Fills {{product_name}}, {{version}} etc. in the selected template
+  const renderPrompt = async (template = systemPrompt, audience?: string) => {
+    if (!template) return "";
+    const vars = {
+      product_name: (await store.get<string>("product_name")) || null,
+      audience: (await store.get<string>("audience")) || audience || null,
+      version: releaseVersion.trim() || null,
+      release_date: releaseDate.trim() || null,
+    };
+    return invoke<string>("render_prompt_cmd", {
+      repoPath, start: startRef, end: endRef, template, vars
+    });
+  };
+
+  const showResult = (result: GenerationResult) => {
+    setUsedCache(result.cached);
+    setReport(result.report);
+    setAiContent(result.cancelled ? result.output + "\n\n[Generation cancelled]" : result.output);
+  };
+
+  // Switching tabs keeps refinements made to the current audience
+  const selectAudience = (name: string) => {
+    const target = audienceOutputs.find(a => a.name === name);
+    if (!target || isGenerating) return;
+    setAudienceOutputs(outputs => outputs.map(a =>
+      a.name === activeAudience ? { ...a, result: { ...a.result, output: aiContent } } : a));
+    setActiveAudience(name);
+    setRenderedPrompt(target.prompt);
+    setReview(null);
+    setIsChatActive(false);
+    showResult(target.result);
+  };
+
+  const toggleExtraAudience = (name: string) => {
+    setExtraAudiences(prev => prev.includes(name) ? prev.filter(n => n !== name) : [...prev, name]);
+  };
+
+  // Renders every selected template and generates them against one context
+  const generateAudiences = async (noCache: boolean, options: Record<string, unknown>) => {
+    const names = [selectedTemplate, ...extraAudiences.filter(n => n !== selectedTemplate)];
+    const prompts: { name: string; prompt: string; template: string }[] = [];
+    for (const name of names) {
+      const template = name === selectedTemplate ? systemPrompt : await invoke<string>("load_template_cmd", { name });
+      prompts.push({ name, prompt: await renderPrompt(template, name), template });
+    }
+    const results = await invoke<{ name: string; result: GenerationResult }[]>("generate_audiences_cmd", {
+      ...options,
+      audiences: prompts.map(p => ({ name: p.name, systemPrompt: p.prompt || undefined, template: p.template || undefined })),
+      noCache
+    });
+    const outputs = results.map(r => ({ ...prompts.find(p => p.name === r.name)!, result: r.result }));
+    setAudienceOutputs(outputs);
+    if (outputs.length > 0) {
+      setActiveAudience(outputs[0].name);
+      setRenderedPrompt(outputs[0].prompt);
+      showResult(outputs[0].result);
+    }
+  };
+
+  const handleGenerate = async (noCache = false) => {
     if (!isTauri) return;
     if (!repoPath || !startRef || !endRef) return;
     setIsGenerating(true);
+    setUsedCache(false);
+    setReport(null);
+    setReview(null);
+    setIsChatActive(false);
+    setAudienceOutputs([]);
+    setActiveAudience("");
     setActiveTab("result");
 
-    let jiraUrl, jiraPat;
+    let jiraUrl, jiraCacheTtl;
     if (isJiraEnabled) {
         jiraUrl = await store.get("jira_url");
-        jiraPat = await store.get("jira_pat");
+        jiraCacheTtl = await store.get("jira_cache_ttl");
+    }
+
+    let prompt = "";
+    try {
+        prompt = await renderPrompt();
+    } catch (e) {
+        setAiContent(`Error in prompt template: ${e}`);
+        setIsGenerating(false);
+        return;
     }
+    setRenderedPrompt(prompt);
 
     if (mode === "manual") {
         setAiContent("Generating Context for Manual Copy...");
         try {
             const res = await invoke("generate_preview_cmd", {
                 repoPath, start: startRef, end: endRef, notes,
+                paths: parsePaths(),
+                contextTemplate: (await store.get("context_template")) || undefined,
+                storedNotesRef: await storedNotesRef(),
+                ...(await redactionOptions()),
+                style: await styleSettings(),
+                template: systemPrompt || undefined,
                 jiraUrl: jiraUrl || undefined,
-                jiraPat: jiraPat || undefined
+                jiraCacheTtl: jiraCacheTtl ?? undefined
             });
             // Use the loaded system prompt instead of placeholder
-            const promptContent = systemPrompt || "[No template loaded]";
+            const promptContent = prompt || "[No template loaded]";
             const fullPrompt = `${promptContent}\n\n*** DATA TO PROCESS ***\n\n${res}`;
             setAiContent(fullPrompt as string);
         } catch (e) {
@@ -290,14 +781,48 @@ function App() {
         }
 
         setAiContent(""); // Clear previous content
+        const ollamaOptions = await store.get("ollama_options");
+        const options = {
+            repoPath, start: startRef, end: endRef, notes, model,
+            paths: parsePaths(),
+            contextTemplate: (await store.get("context_template")) || undefined,
+            storedNotesRef: await storedNotesRef(),
+            ...(await redactionOptions()),
+            style: await styleSettings(),
+            ollamaOptions: ollamaOptions || undefined,
+            structured: isStructured,
+            outputFormat,
+            jiraUrl: jiraUrl || undefined,
+            jiraCacheTtl: jiraCacheTtl ?? undefined,
+            autoRepair
+        };
+        if (extraAudiences.some(n => n !== selectedTemplate)) {
+            try {
+                await generateAudiences(noCache, options);
+            } catch (e) {
+                setAiContent(prev => prev + `\n\nError generating release notes: ${e}`);
+            } finally {
+                setIsGenerating(false);
+            }
+            return;
+        }
         try {
             // Pass the loaded system prompt content
-            await invoke("generate_ai_cmd", {
-                repoPath, start: startRef, end: endRef, notes, model, 
-                systemPrompt: systemPrompt || undefined,
-                jiraUrl: jiraUrl || undefined,
-                jiraPat: jiraPat || undefined
+            const result = await invoke<GenerationResult>("generate_ai_cmd", {
+                ...options,
+                systemPrompt: prompt || undefined,
+                template: systemPrompt || undefined,
+                noCache
             });
+            setUsedCache(result.cached);
+            setReport(result.report);
+            if (result.report.repaired) {
+                // The repair pass is not streamed; show the corrected notes
+                setAiContent(result.output);
+            }
+            if (result.cancelled) {
+                setAiContent(prev => prev + "\n\n[Generation cancelled]");
+            }
         } catch (e) {
             setAiContent(prev => prev + `\n\nError generating release notes: ${e}`);
         } finally {
@@ -306,6 +831,58 @@ function App() {
     }
   };
 
+  const handleReview = async () => {
+    if (!isTauri || !aiContent) return;
+    if (review) {
+      setReview(null);
+      return;
+    }
+    setActiveLocale("");
+    try {
+      const result = await invoke<ReviewResult>("review_notes_cmd", { output: aiContent, notes });
+      setReview(result);
+    } catch (e) {
+      alert(`Review failed: ${e}`);
+    }
+  };
+
+  const handleRefine = async () => {
+    if (!isTauri || !refineInput.trim() || !aiContent) return;
+    setIsGenerating(true);
+    setReview(null);
+    try {
+      // The first instruction starts a chat seeded with the current draft
+      if (!isChatActive) {
+        const ollamaOptions = await store.get("ollama_options");
+        await invoke("start_chat_cmd", {
+          model,
+          systemPrompt: renderedPrompt || undefined,
+          ollamaOptions: ollamaOptions || undefined,
+          draft: aiContent
+        });
+        setIsChatActive(true);
+      }
+      const previous = aiContent;
+      setAiContent("");
+      const result = await invoke<{ output: string; cancelled: boolean }>("continue_chat_cmd", { message: refineInput });
+      if (result.cancelled) setAiContent(previous);
+      else setRefineInput("");
+    } catch (e) {
+      setAiContent(prev => prev + `\n\nError refining release notes: ${e}`);
+    } finally {
+      setIsGenerating(false);
+    }
+  };
+
+  const handleCancel = async () => {
+    if (!isTauri) return;
+    try {
+      await invoke("cancel_generation_cmd");
+    } catch (e) {
+      console.error("Failed to cancel generation:", e);
+    }
+  };
+
   // Handle Ctrl+Enter for Generate
   useEffect(() => {
     const handleKeyDown = (event: KeyboardEvent) => {
@@ -374,6 +951,38 @@ function App() {
             </div>
           </div>
 
+          {/* Path Filter */}
+          <div className="space-y-2">
+            <label className="text-xs font-medium text-slate-500 uppercase tracking-wider">Paths</label>
+            <input
+              placeholder="All paths (e.g. src/, !src/tests)"
+              className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+              value={pathFilter}
+              onChange={(e) => setPathFilter(e.target.value)}
+            />
+          </div>
+
+          {/* Release Details */}
+          <div className="space-y-2">
+            <label className="text-xs font-medium text-slate-500 uppercase tracking-wider">Release</label>
+            <div className="grid grid-cols-2 gap-2">
+              <input
+                placeholder="Version (tag of end)"
+                title="Fills {{version}} in the prompt template"
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={releaseVersion}
+                onChange={(e) => setReleaseVersion(e.target.value)}
+              />
+              <input
+                placeholder="Date (of end commit)"
+                title="Fills {{release_date}} in the prompt template"
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={releaseDate}
+                onChange={(e) => setReleaseDate(e.target.value)}
+              />
+            </div>
+          </div>
+
           {/* Mode Selection */}
            <div className="space-y-2">
             <label className="text-xs font-medium text-slate-500 uppercase tracking-wider">Generation Mode</label>
@@ -428,14 +1037,69 @@ function App() {
                         value={selectedTemplate}
                         onChange={(e) => setSelectedTemplate(e.target.value)}
                      >
-                        {templates.map(t => <option key={t} value={t}>{t}</option>)}
+                        {templates.map(t => <option key={t.name} value={t.name}>{t.builtin ? t.name : `${t.name} (custom)`}</option>)}
                      </select>
                      <div className="absolute right-3 top-2.5 pointer-events-none">
                          <svg className="w-4 h-4 text-slate-400" fill="none" stroke="currentColor" viewBox="0 0 24 24"><path strokeLinecap="round" strokeLinejoin="round" strokeWidth="2" d="M19 9l-7 7-7-7"></path></svg>
                      </div>
                  </div>
+                 {mode === "auto" && templates.length > 1 && (
+                     <div className="pt-1 space-y-1">
+                         <label className="text-[10px] font-medium text-slate-400" title="Generate notes for these templates too, from the same context">Also Generate For</label>
+                         <div className="flex flex-wrap gap-x-3 gap-y-1">
+                             {templates.filter(t => t.name !== selectedTemplate).map(t => (
+                                 <label key={t.name} className="flex items-center gap-1 text-[10px] text-slate-600">
+                                     <input
+                                        type="checkbox"
+                                        checked={extraAudiences.includes(t.name)}
+                                        onChange={() => toggleExtraAudience(t.name)}
+                                     />
+                                     {t.name}
+                                 </label>
+                             ))}
+                         </div>
+                     </div>
+                 )}
               </div>
 
+              {/* Structured Output (Only in Auto Mode) */}
+              {mode === "auto" && (
+                  <div className="space-y-2">
+                      <div className="flex items-center justify-between p-2 bg-slate-50 rounded-md border border-slate-100">
+                          <span className="text-[10px] font-medium text-slate-600" title="Ask the model for validated JSON and render it">Structured Output</span>
+                          <button 
+                            onClick={() => setIsStructured(!isStructured)}
+                            className={`w-8 h-4 rounded-full relative transition-colors ${isStructured ? 'bg-blue-500' : 'bg-slate-300'}`}
+                          >
+                              <div className={`absolute top-0.5 w-3 h-3 bg-white rounded-full shadow-sm transition-all ${isStructured ? 'left-4.5' : 'left-0.5'}`} />
+                          </button>
+                      </div>
+                      {isStructured && (
+                          <select
+                            className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                            value={outputFormat}
+                            onChange={(e) => setOutputFormat(e.target.value as NotesFormat)}
+                          >
+                            <option value="markdown">Markdown</option>
+                            <option value="html">HTML</option>
+                            <option value="asciidoc">AsciiDoc</option>
+                            <option value="rst">reStructuredText</option>
+                            <option value="text">Plain Text</option>
+                            <option value="json">JSON</option>
+                          </select>
+                      )}
+                      <div className="flex items-center justify-between p-2 bg-slate-50 rounded-md border border-slate-100">
+                          <span className="text-[10px] font-medium text-slate-600" title="If the notes break the template, ask the model once to fix them">Auto-Repair</span>
+                          <button 
+                            onClick={() => setAutoRepair(!autoRepair)}
+                            className={`w-8 h-4 rounded-full relative transition-colors ${autoRepair ? 'bg-blue-500' : 'bg-slate-300'}`}
+                          >
+                              <div className={`absolute top-0.5 w-3 h-3 bg-white rounded-full shadow-sm transition-all ${autoRepair ? 'left-4.5' : 'left-0.5'}`} />
+                          </button>
+                      </div>
+                  </div>
+              )}
+
               {/* AI Settings (Only in Auto Mode) */}
               {mode === "auto" && (
                   <div className="space-y-1">
@@ -473,7 +1137,7 @@ function App() {
         {/* Footer Actions */}
         <div className="p-4 border-t border-slate-200 bg-slate-50">
             <button
-                onClick={handleGenerate}
+                onClick={() => handleGenerate()}
                 disabled={isGenerating || !repoPath || !startRef || !endRef || (mode === "auto" && ollamaStatus !== "ok")}
                 className={`w-full flex items-center justify-center gap-2 px-4 py-2.5 rounded-md font-medium text-sm text-white shadow-sm transition-all
                     ${isGenerating || !repoPath || !startRef || !endRef || (mode === "auto" && ollamaStatus !== "ok")
@@ -484,6 +1148,15 @@ function App() {
                 {isGenerating ? <Loader2 size={16} className="animate-spin" /> : (mode === "auto" ? <Play size={16} /> : <Copy size={16} />)}
                 {isGenerating ? "Processing..." : (mode === "auto" ? "Generate with AI" : "Generate Prompt")}
             </button>
+            {isGenerating && mode === "auto" && (
+                <button
+                    onClick={handleCancel}
+                    className="mt-2 w-full flex items-center justify-center gap-2 px-4 py-2 rounded-md font-medium text-sm text-rose-700 bg-white border border-rose-200 hover:bg-rose-50 transition-colors"
+                >
+                    <Square size={14} />
+                    Stop Generation
+                </button>
+            )}
         </div>
       </aside>
 
@@ -554,9 +1227,63 @@ function App() {
             {activeTab === "result" && (
                 <div className="p-8">
                     <div className="max-w-3xl mx-auto">
+                         {(audienceOutputs.length > 1 || (isGenerating && activeAudience)) && (
+                             <div className="flex items-center gap-1 mb-4 border-b border-slate-200">
+                                 {audienceOutputs.length > 1 ? audienceOutputs.map(a => (
+                                     <button
+                                        key={a.name}
+                                        onClick={() => selectAudience(a.name)}
+                                        disabled={isGenerating}
+                                        className={`px-3 py-1.5 text-xs font-medium border-b-2 -mb-px transition-colors ${activeAudience === a.name ? 'border-blue-500 text-blue-600' : 'border-transparent text-slate-500 hover:text-slate-700'}`}
+                                     >
+                                        {a.name}
+                                     </button>
+                                 )) : (
+                                     <span className="px-3 py-1.5 text-xs font-medium text-slate-500">Generating: {activeAudience}</span>
+                                 )}
+                             </div>
+                         )}
                          {aiContent ? (
                              <div className="space-y-4">
                                 <div className="flex items-center justify-end gap-2">
+                                    {usedCache && (
+                                        <div className="mr-auto flex items-center gap-2">
+                                            <span className="flex items-center gap-1.5 px-2 py-1 text-xs font-medium text-amber-700 bg-amber-50 border border-amber-200 rounded" title="This result was loaded from the local cache">
+                                                <Database size={12} />
+                                                Cached result
+                                            </span>
+                                            <button
+                                                onClick={() => handleGenerate(true)}
+                                                disabled={isGenerating}
+                                                className="text-xs text-blue-500 hover:text-blue-700 font-medium hover:underline"
+                                            >
+                                                Regenerate
+                                            </button>
+                                        </div>
+                                    )}
+                                    {mode === "auto" && locales.length > 0 && (
+                                        <select
+                                            className="px-2 py-1.5 text-sm text-slate-600 bg-white border border-slate-300 rounded shadow-sm focus:outline-none"
+                                            value={activeLocale}
+                                            disabled={isGenerating}
+                                            onChange={(e) => selectLocale(e.target.value)}
+                                            title="Show the notes translated into another language"
+                                        >
+                                            <option value="">Original</option>
+                                            {locales.map(l => <option key={l} value={l}>{l}</option>)}
+                                        </select>
+                                    )}
+                                    {mode === "auto" && (
+                                        <button
+                                            onClick={handleReview}
+                                            disabled={isGenerating}
+                                            className={`flex items-center gap-2 px-3 py-1.5 text-sm font-medium border rounded transition-colors shadow-sm ${review ? 'text-blue-700 bg-blue-50 border-blue-300' : 'text-slate-600 bg-white border-slate-300 hover:bg-slate-50'}`}
+                                            title="Check each bullet against the commits and Jira issues"
+                                        >
+                                            <ShieldCheck size={14} />
+                                            Review
+                                        </button>
+                                    )}
                                     <button
                                         onClick={handleCopy}
                                         className="flex items-center gap-2 px-3 py-1.5 text-sm font-medium text-slate-600 bg-white border border-slate-300 rounded hover:bg-slate-50 transition-colors shadow-sm"
@@ -573,10 +1300,158 @@ function App() {
                                         <Save size={14} />
                                         Save
                                     </button>
+                                    {mode === "auto" && (!isStructured || outputFormat === "markdown") && (
+                                        <div className="relative">
+                                            <button
+                                                onClick={() => setIsExportOpen(!isExportOpen)}
+                                                disabled={isGenerating}
+                                                className="flex items-center gap-2 px-3 py-1.5 text-sm font-medium text-slate-600 bg-white border border-slate-300 rounded hover:bg-slate-50 transition-colors shadow-sm"
+                                                title="Convert the notes and save them in another format"
+                                            >
+                                                Export
+                                                <ChevronDown size={14} />
+                                            </button>
+                                            {isExportOpen && (
+                                                <div className="absolute right-0 mt-1 w-44 bg-white border border-slate-200 rounded-md shadow-lg z-10 py-1">
+                                                    {EXPORT_FORMATS.map(f => (
+                                                        <button
+                                                            key={f.format}
+                                                            onClick={() => handleExport(f)}
+                                                            className="block w-full text-left px-3 py-1.5 text-sm text-slate-600 hover:bg-slate-50"
+                                                        >
+                                                            {f.label}
+                                                        </button>
+                                                    ))}
+                                                </div>
+                                            )}
+                                        </div>
+                                    )}
+                                    {mode === "auto" && (!isStructured || outputFormat === "markdown") && (
+                                        <button
+                                            onClick={handlePublish}
+                                            disabled={isGenerating || isPublishing}
+                                            className="flex items-center gap-2 px-3 py-1.5 text-sm font-medium text-slate-600 bg-white border border-slate-300 rounded hover:bg-slate-50 transition-colors shadow-sm"
+                                            title="Create or update the GitHub/GitLab release for the end tag"
+                                        >
+                                            {isPublishing ? <Loader2 size={14} className="animate-spin" /> : <Rocket size={14} />}
+                                            Publish
+                                        </button>
+                                    )}
+                                    {mode === "auto" && (!isStructured || outputFormat === "markdown") && (
+                                        <button
+                                            onClick={handleConfluence}
+                                            disabled={isGenerating || isPublishing}
+                                            className="flex items-center gap-2 px-3 py-1.5 text-sm font-medium text-slate-600 bg-white border border-slate-300 rounded hover:bg-slate-50 transition-colors shadow-sm"
+                                            title="Create or update the release notes page in Confluence"
+                                        >
+                                            <Upload size={14} />
+                                            Confluence
+                                        </button>
+                                    )}
+                                    {mode === "auto" && (report?.context.issue_keys.length ?? 0) > 0 && (
+                                        <button
+                                            onClick={handleJiraWrite}
+                                            disabled={isGenerating || isPublishing}
+                                            className="flex items-center gap-2 px-3 py-1.5 text-sm font-medium text-slate-600 bg-white border border-slate-300 rounded hover:bg-slate-50 transition-colors shadow-sm"
+                                            title="Update the fix version and linked issues in Jira"
+                                        >
+                                            <Send size={14} />
+                                            Jira
+                                        </button>
+                                    )}
+                                    {mode === "auto" && (!isStructured || outputFormat === "markdown") && (
+                                        <button
+                                            onClick={handleNotify}
+                                            disabled={isGenerating || isPublishing}
+                                            className="flex items-center gap-2 px-3 py-1.5 text-sm font-medium text-slate-600 bg-white border border-slate-300 rounded hover:bg-slate-50 transition-colors shadow-sm"
+                                            title="Post the executive summary to Slack, Teams or another webhook"
+                                        >
+                                            <Bell size={14} />
+                                            Notify
+                                        </button>
+                                    )}
+                                    {mode === "auto" && (!isStructured || outputFormat === "markdown") && (
+                                        <button
+                                            onClick={handleStoreNotes}
+                                            disabled={isGenerating || isPublishing}
+                                            className="flex items-center gap-2 px-3 py-1.5 text-sm font-medium text-slate-600 bg-white border border-slate-300 rounded hover:bg-slate-50 transition-colors shadow-sm"
+                                            title="Keep the notes in the end tag or in git notes"
+                                        >
+                                            <Tag size={14} />
+                                            Store
+                                        </button>
+                                    )}
                                 </div>
                                 <div className="prose prose-slate prose-sm max-w-none bg-white p-8 rounded-lg border border-slate-200 shadow-sm">
-                                     <pre className="whitespace-pre-wrap font-sans">{aiContent}</pre>
+                                     <pre className="whitespace-pre-wrap font-sans">{review ? review.annotated : shownContent}</pre>
                                 </div>
+                                {activeLocale && (translations[activeLocale]?.check.problems.length ?? 0) > 0 && (
+                                    <div className="bg-amber-50 rounded-lg border border-amber-200 px-4 py-3 text-xs text-amber-800">
+                                        <div className="font-medium mb-1">The {activeLocale} translation may be incomplete</div>
+                                        <ul className="list-disc pl-4 space-y-0.5">
+                                            {translations[activeLocale].check.problems.map((p, i) => <li key={i}>{p}</li>)}
+                                        </ul>
+                                    </div>
+                                )}
+                                {review && (
+                                    <div className="text-xs text-slate-500">
+                                        {review.report.bullets.filter(b => b.evidence.length > 0).length} of {review.report.bullets.length} bullets are supported by commits, issues or notes.
+                                        {" "}Bullets marked ⚠ UNSUPPORTED could not be traced and should be checked before publishing.
+                                    </div>
+                                )}
+                                {mode === "auto" && (
+                                    <div className="flex items-center gap-2">
+                                        <input
+                                            placeholder='Refine the draft, e.g. "make the bug fixes section shorter"'
+                                            className="flex-1 px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                                            value={refineInput}
+                                            disabled={isGenerating}
+                                            onChange={(e) => setRefineInput(e.target.value)}
+                                            onKeyDown={(e) => { if (e.key === "Enter") handleRefine(); }}
+                                        />
+                                        <button
+                                            onClick={handleRefine}
+                                            disabled={isGenerating || !refineInput.trim()}
+                                            className="flex items-center gap-2 px-3 py-2 text-sm font-medium text-white bg-slate-900 hover:bg-slate-800 rounded-md shadow-sm transition-all disabled:bg-slate-300"
+                                        >
+                                            <Send size={14} />
+                                            Refine
+                                        </button>
+                                    </div>
+                                )}
+                                {report && report.violations.length > 0 && (
+                                    <div className="bg-amber-50 rounded-lg border border-amber-200 px-4 py-3 text-xs text-amber-800">
+                                        <div className="font-medium mb-1">
+                                            Validation found {report.violations.length} problem(s){report.repaired ? " after repair" : ""}
+                                        </div>
+                                        <ul className="list-disc pl-4 space-y-0.5">
+                                            {report.violations.map((v, i) => <li key={i}>{v.message}</li>)}
+                                        </ul>
+                                    </div>
+                                )}
+                                {report && (
+                                    <details className="bg-white rounded-lg border border-slate-200 shadow-sm text-xs text-slate-600">
+                                        <summary className="px-4 py-2 cursor-pointer font-medium text-slate-700">Run Report</summary>
+                                        <dl className="grid grid-cols-2 gap-x-6 gap-y-1 px-4 pb-4">
+                                            <dt>Commits</dt><dd>{report.context.commit_count} ({report.context.diff_files} files, {report.context.diff_bytes} bytes of diff)</dd>
+                                            <dt>Prompt size</dt><dd>{report.context.context_bytes + report.system_prompt_bytes} bytes (~{report.estimated_prompt_tokens} tokens)</dd>
+                                            {report.context.style_examples.length > 0 && (
+                                                <><dt>Style examples</dt><dd>{report.context.style_examples.join(", ")}</dd></>
+                                            )}
+                                            {report.context.redactions.length > 0 && (
+                                                <><dt>Redacted</dt><dd>{report.context.redactions.map(r => `${r.count} × ${r.detector}`).join(", ")}</dd></>
+                                            )}
+                                            {report.context.jira && (
+                                                <><dt>Jira issues</dt><dd>{report.context.jira.fetched} fetched, {report.context.jira.cached} cached, {report.context.jira.stale} stale, {report.context.jira.failed} failed</dd></>
+                                            )}
+                                            <dt>Context time</dt><dd>{report.context_ms} ms</dd>
+                                            <dt>Generation time</dt><dd>{report.cache_hit ? "cached" : `${report.generation_ms} ms`}</dd>
+                                            {report.ollama && (
+                                                <><dt>Ollama tokens</dt><dd>{report.ollama.prompt_eval_count ?? 0} prompt, {report.ollama.eval_count ?? 0} generated</dd></>
+                                            )}
+                                        </dl>
+                                    </details>
+                                )}
                              </div>
                          ) : (
                             <div className="p-12 text-center text-slate-400">
@@ -589,7 +1464,7 @@ function App() {
         </div>
       </main>
       
-      <SettingsModal isOpen={isSettingsOpen} onClose={() => setIsSettingsOpen(false)} />
+      <SettingsModal isOpen={isSettingsOpen} onClose={() => { setIsSettingsOpen(false); loadLocales(); }} />
     </div>
   );
 }
@@ -1,9 +1,27 @@
 import { useState, useEffect } from "react";
 import { X, Check, AlertCircle } from "lucide-react";
 import { LazyStore } from "@tauri-apps/plugin-store";
+import { invoke } from "@tauri-apps/api/core";
 
 const store = new LazyStore("settings.dat");
 
+export interface OllamaOptions {
+  temperature?: number;
+  top_p?: number;
+  num_ctx?: number;
+  seed?: number;
+  repeat_penalty?: number;
+  keep_alive?: string;
+}
+
+const OPTION_FIELDS: { key: keyof OllamaOptions; label: string; placeholder: string; step?: string }[] = [
+  { key: "temperature", label: "Temperature", placeholder: "0.8", step: "0.1" },
+  { key: "top_p", label: "Top P", placeholder: "0.9", step: "0.05" },
+  { key: "num_ctx", label: "Context Size", placeholder: "2048" },
+  { key: "seed", label: "Seed", placeholder: "Random" },
+  { key: "repeat_penalty", label: "Repeat Penalty", placeholder: "1.1", step: "0.05" },
+];
+
 interface SettingsModalProps {
   isOpen: boolean;
   onClose: () => void;
@@ -12,6 +30,38 @@ interface SettingsModalProps {
 export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
   const [jiraUrl, setJiraUrl] = useState("");
   const [jiraPat, setJiraPat] = useState("");
+  const [jiraPatSource, setJiraPatSource] = useState<string | null>(null);
+  const [jiraCacheMinutes, setJiraCacheMinutes] = useState("60");
+  const [cacheMessage, setCacheMessage] = useState("");
+  const [modelOptions, setModelOptions] = useState<Record<string, string>>({});
+  const [productName, setProductName] = useState("");
+  const [audience, setAudience] = useState("");
+  const [contextTemplate, setContextTemplate] = useState("");
+  const [locales, setLocales] = useState("");
+  const [glossary, setGlossary] = useState("");
+  const [forge, setForge] = useState("");
+  const [forgeRepo, setForgeRepo] = useState("");
+  const [forgeUrl, setForgeUrl] = useState("");
+  const [forgeToken, setForgeToken] = useState("");
+  const [forgeTokenSource, setForgeTokenSource] = useState<string | null>(null);
+  const [attachContext, setAttachContext] = useState(false);
+  const [confluenceUrl, setConfluenceUrl] = useState("");
+  const [jiraWriteComment, setJiraWriteComment] = useState(false);
+  const [webhookUrl, setWebhookUrl] = useState("");
+  const [webhookHost, setWebhookHost] = useState<string | null>(null);
+  const [webhookFormat, setWebhookFormat] = useState("");
+  const [webhookTemplate, setWebhookTemplate] = useState("");
+  const [notesStore, setNotesStore] = useState("git-notes");
+  const [notesRef, setNotesRef] = useState("");
+  const [useStoredNotes, setUseStoredNotes] = useState(false);
+  const [styleExamples, setStyleExamples] = useState("");
+  const [redact, setRedact] = useState(true);
+  const [redactPatterns, setRedactPatterns] = useState("");
+  const [styleCount, setStyleCount] = useState("");
+  const [styleMaxChars, setStyleMaxChars] = useState("");
+  const [jiraWriteLabel, setJiraWriteLabel] = useState("");
+  const [confluenceSpace, setConfluenceSpace] = useState("");
+  const [confluenceParent, setConfluenceParent] = useState("");
   const [isSaving, setIsSaving] = useState(false);
 
   useEffect(() => {
@@ -20,32 +70,218 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
     }
   }, [isOpen]);
 
+  // Each forge has its own token, so look it up again when the forge changes
+  useEffect(() => {
+    if (!isOpen || !forge) {
+      setForgeTokenSource(null);
+      return;
+    }
+    invoke<string | null>("forge_token_source_cmd", { forge })
+      .then(setForgeTokenSource)
+      .catch(() => setForgeTokenSource(null));
+  }, [isOpen, forge]);
+
   const loadSettings = async () => {
     try {
       const savedUrl = await store.get("jira_url");
       if (savedUrl) setJiraUrl(savedUrl as string);
       
-      const savedPat = await store.get("jira_pat");
-      if (savedPat) setJiraPat(savedPat as string);
+      // The token itself stays in the backend; only its source is shown
+      setJiraPat("");
+      setJiraPatSource(await invoke<string | null>("jira_pat_source_cmd"));
+
+      const savedOptions = await store.get("ollama_options");
+      if (savedOptions) {
+        setModelOptions(Object.fromEntries(
+          Object.entries(savedOptions as OllamaOptions).map(([k, v]) => [k, String(v)])
+        ));
+      }
+
+      const savedProduct = await store.get("product_name");
+      if (savedProduct) setProductName(savedProduct as string);
+
+      const savedAudience = await store.get("audience");
+      if (savedAudience) setAudience(savedAudience as string);
+
+      const savedLocales = await store.get("locales");
+      if (savedLocales) setLocales(savedLocales as string);
+
+      const savedGlossary = await store.get("glossary");
+      if (savedGlossary) setGlossary(savedGlossary as string);
+
+      const savedForge = await store.get("forge");
+      if (savedForge) setForge(savedForge as string);
+      const savedForgeRepo = await store.get("forge_repo");
+      if (savedForgeRepo) setForgeRepo(savedForgeRepo as string);
+      const savedForgeUrl = await store.get("forge_url");
+      if (savedForgeUrl) setForgeUrl(savedForgeUrl as string);
+      setForgeToken("");
+      const savedAttach = await store.get("attach_context");
+      if (savedAttach !== null && savedAttach !== undefined) setAttachContext(savedAttach as boolean);
+
+      // Like the tokens, the webhook URL stays in the backend; only its host is shown
+      setWebhookUrl("");
+      setWebhookHost(await invoke<string | null>("webhook_host_cmd"));
+      const savedWebhookFormat = await store.get("webhook_format");
+      if (savedWebhookFormat) setWebhookFormat(savedWebhookFormat as string);
+      const savedWebhookTemplate = await store.get("webhook_template");
+      if (savedWebhookTemplate) setWebhookTemplate(savedWebhookTemplate as string);
+
+      const savedNotesStore = await store.get("notes_store");
+      if (savedNotesStore) setNotesStore(savedNotesStore as string);
+      const savedNotesRef = await store.get("notes_ref");
+      if (savedNotesRef) setNotesRef(savedNotesRef as string);
+      const savedUseStored = await store.get("use_stored_notes");
+      if (savedUseStored !== null && savedUseStored !== undefined) setUseStoredNotes(savedUseStored as boolean);
+      const savedRedact = await store.get("redact");
+      if (savedRedact !== null && savedRedact !== undefined) setRedact(savedRedact as boolean);
+      const savedPatterns = await store.get("redact_patterns");
+      if (savedPatterns) setRedactPatterns(savedPatterns as string);
+      const savedStyle = await store.get("style_examples");
+      if (savedStyle) setStyleExamples(savedStyle as string);
+      const savedStyleCount = await store.get("style_count");
+      if (savedStyleCount !== null && savedStyleCount !== undefined) setStyleCount(String(savedStyleCount));
+      const savedStyleMax = await store.get("style_max_chars");
+      if (savedStyleMax !== null && savedStyleMax !== undefined) setStyleMaxChars(String(savedStyleMax));
+
+      const savedConfluenceUrl = await store.get("confluence_url");
+      if (savedConfluenceUrl) setConfluenceUrl(savedConfluenceUrl as string);
+      const savedSpace = await store.get("confluence_space");
+      if (savedSpace) setConfluenceSpace(savedSpace as string);
+      const savedParent = await store.get("confluence_parent");
+      if (savedParent) setConfluenceParent(savedParent as string);
+
+      const savedLayout = await store.get("context_template");
+      if (savedLayout) setContextTemplate(savedLayout as string);
+
+      const savedWriteComment = await store.get("jira_write_comment");
+      if (savedWriteComment !== null && savedWriteComment !== undefined) setJiraWriteComment(savedWriteComment as boolean);
+      const savedWriteLabel = await store.get("jira_write_label");
+      if (savedWriteLabel) setJiraWriteLabel(savedWriteLabel as string);
+
+      const savedTtl = await store.get("jira_cache_ttl");
+      if (savedTtl !== null && savedTtl !== undefined) setJiraCacheMinutes(String(Math.round((savedTtl as number) / 60)));
     } catch (e) {
       console.warn("Failed to load Jira settings:", e);
     }
   };
 
+  const handleForgetPat = async () => {
+    try {
+      await invoke("save_jira_pat_cmd", { pat: "" });
+      setJiraPatSource(await invoke<string | null>("jira_pat_source_cmd"));
+    } catch (err) {
+      alert(`${err}`);
+    }
+  };
+
+  const handleForgetForgeToken = async () => {
+    try {
+      await invoke("save_forge_token_cmd", { forge, token: "" });
+      setForgeTokenSource(await invoke<string | null>("forge_token_source_cmd", { forge }));
+    } catch (err) {
+      alert(`${err}`);
+    }
+  };
+
+  const handleForgetWebhook = async () => {
+    try {
+      await invoke("save_webhook_url_cmd", { url: "" });
+      setWebhookHost(await invoke<string | null>("webhook_host_cmd"));
+    } catch (err) {
+      alert(`${err}`);
+    }
+  };
+
   const handleSave = async () => {
     setIsSaving(true);
     await store.set("jira_url", jiraUrl);
-    await store.set("jira_pat", jiraPat);
+    if (jiraPat.trim()) {
+      try {
+        await invoke("save_jira_pat_cmd", { pat: jiraPat });
+      } catch (err) {
+        alert(`${err}`);
+        setIsSaving(false);
+        return;
+      }
+    }
+    if (forge && forgeToken.trim()) {
+      try {
+        await invoke("save_forge_token_cmd", { forge, token: forgeToken });
+      } catch (err) {
+        alert(`${err}`);
+        setIsSaving(false);
+        return;
+      }
+    }
+    if (webhookUrl.trim()) {
+      try {
+        await invoke("save_webhook_url_cmd", { url: webhookUrl });
+      } catch (err) {
+        alert(`${err}`);
+        setIsSaving(false);
+        return;
+      }
+    }
+    const minutes = parseInt(jiraCacheMinutes, 10);
+    await store.set("jira_cache_ttl", isNaN(minutes) ? 3600 : Math.max(0, minutes) * 60);
+    // Only send options the user actually set, so the model defaults apply otherwise
+    const options: OllamaOptions = {};
+    for (const { key } of OPTION_FIELDS) {
+      const value = parseFloat(modelOptions[key] ?? "");
+      if (!isNaN(value)) (options as Record<string, number>)[key] = value;
+    }
+    if (modelOptions.keep_alive?.trim()) options.keep_alive = modelOptions.keep_alive.trim();
+    await store.set("ollama_options", options);
+    await store.set("product_name", productName.trim());
+    await store.set("audience", audience.trim());
+    await store.set("context_template", contextTemplate);
+    await store.set("locales", locales.trim());
+    await store.set("glossary", glossary);
+    await store.set("forge", forge);
+    await store.set("forge_repo", forgeRepo.trim());
+    await store.set("forge_url", forgeUrl.trim());
+    await store.set("attach_context", attachContext);
+    await store.set("jira_write_comment", jiraWriteComment);
+    await store.set("jira_write_label", jiraWriteLabel.trim());
+    await store.set("webhook_format", webhookFormat);
+    await store.set("webhook_template", webhookTemplate);
+    await store.set("notes_store", notesStore);
+    await store.set("notes_ref", notesRef.trim());
+    await store.set("use_stored_notes", useStoredNotes);
+    await store.set("redact", redact);
+    await store.set("redact_patterns", redactPatterns);
+    await store.set("style_examples", styleExamples.trim());
+    const count = parseInt(styleCount, 10);
+    await store.set("style_count", isNaN(count) ? null : Math.max(0, count));
+    const maxChars = parseInt(styleMaxChars, 10);
+    await store.set("style_max_chars", isNaN(maxChars) ? null : Math.max(0, maxChars));
+    await store.set("confluence_url", confluenceUrl.trim());
+    await store.set("confluence_space", confluenceSpace.trim());
+    await store.set("confluence_parent", confluenceParent.trim());
     await store.save(); // Ensure persistence
     setIsSaving(false);
     onClose();
   };
 
+  const handleLoadDefaultLayout = async () => {
+    setContextTemplate(await invoke<string>("default_context_template_cmd"));
+  };
+
+  const handleClearCache = async () => {
+    try {
+      const removed = await invoke<number>("clear_jira_cache_cmd");
+      setCacheMessage(`Cleared ${removed} cached issue(s).`);
+    } catch (e) {
+      setCacheMessage(`Failed to clear cache: ${e}`);
+    }
+  };
+
   if (!isOpen) return null;
 
   return (
     <div className="fixed inset-0 z-50 flex items-center justify-center bg-black/50 backdrop-blur-sm">
-      <div className="w-full max-w-md bg-white rounded-xl shadow-2xl border border-slate-200 p-6 space-y-6">
+      <div className="w-full max-w-md bg-white rounded-xl shadow-2xl border border-slate-200 p-6 space-y-6 max-h-[90vh] overflow-y-auto">
         
         {/* Header */}
         <div className="flex items-center justify-between">
@@ -55,6 +291,335 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
           </button>
         </div>
 
+        {/* Model Options Section */}
+        <div className="space-y-4">
+          <div className="flex items-center gap-2 text-sm font-medium text-slate-700 border-b border-slate-100 pb-2">
+            <span className="w-2 h-2 rounded-full bg-emerald-500"></span>
+            Model Options
+          </div>
+
+          <div className="grid grid-cols-3 gap-3">
+            {OPTION_FIELDS.map(({ key, label, placeholder, step }) => (
+              <div key={key} className="space-y-1">
+                <label className="text-xs font-medium text-slate-600">{label}</label>
+                <input
+                  type="number"
+                  step={step ?? "1"}
+                  placeholder={placeholder}
+                  className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                  value={modelOptions[key] ?? ""}
+                  onChange={(e) => setModelOptions({ ...modelOptions, [key]: e.target.value })}
+                />
+              </div>
+            ))}
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Keep Alive</label>
+              <input
+                placeholder="5m"
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={modelOptions.keep_alive ?? ""}
+                onChange={(e) => setModelOptions({ ...modelOptions, keep_alive: e.target.value })}
+              />
+            </div>
+          </div>
+          <p className="text-[10px] text-slate-500">Leave blank to use the model defaults. Temperature 0 with a fixed seed gives reproducible notes.</p>
+        </div>
+
+        {/* Prompt Variables Section */}
+        <div className="space-y-4">
+          <div className="flex items-center gap-2 text-sm font-medium text-slate-700 border-b border-slate-100 pb-2">
+            <span className="w-2 h-2 rounded-full bg-amber-500"></span>
+            Prompt Variables
+          </div>
+
+          <div className="grid grid-cols-2 gap-3">
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Product Name</label>
+              <input
+                placeholder="Acme Portal"
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={productName}
+                onChange={(e) => setProductName(e.target.value)}
+              />
+            </div>
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Audience</label>
+              <input
+                placeholder="End users"
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={audience}
+                onChange={(e) => setAudience(e.target.value)}
+              />
+            </div>
+          </div>
+          <p className="text-[10px] text-slate-500">{"Used for {{product_name}} and {{audience}} in prompt templates."}</p>
+        </div>
+
+        {/* Translation Section */}
+        <div className="space-y-4">
+          <div className="flex items-center gap-2 text-sm font-medium text-slate-700 border-b border-slate-100 pb-2">
+            <span className="w-2 h-2 rounded-full bg-rose-500"></span>
+            Translation
+          </div>
+
+          <div className="space-y-1">
+            <label className="text-xs font-medium text-slate-600">Locales</label>
+            <input
+              placeholder="de, ja, fr"
+              className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+              value={locales}
+              onChange={(e) => setLocales(e.target.value)}
+            />
+          </div>
+          <div className="space-y-1">
+            <label className="text-xs font-medium text-slate-600">Glossary</label>
+            <textarea
+              rows={3}
+              placeholder={"Acme Portal\nSmartSync"}
+              className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-xs font-mono focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+              value={glossary}
+              onChange={(e) => setGlossary(e.target.value)}
+            />
+          </div>
+          <p className="text-[10px] text-slate-500">Locales offered in the result view. Glossary terms (one per line) are never translated; issue keys, CVE IDs, code and links are always kept.</p>
+        </div>
+
+        {/* Release Publishing Section */}
+        <div className="space-y-4">
+          <div className="flex items-center gap-2 text-sm font-medium text-slate-700 border-b border-slate-100 pb-2">
+            <span className="w-2 h-2 rounded-full bg-amber-500"></span>
+            Release Publishing
+          </div>
+
+          <div className="grid grid-cols-2 gap-3">
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Forge</label>
+              <select
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={forge}
+                onChange={(e) => setForge(e.target.value)}
+              >
+                <option value="">None</option>
+                <option value="github">GitHub</option>
+                <option value="gitlab">GitLab</option>
+              </select>
+            </div>
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Repository</label>
+              <input
+                placeholder="From the origin remote"
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={forgeRepo}
+                onChange={(e) => setForgeRepo(e.target.value)}
+              />
+            </div>
+          </div>
+          <div className="space-y-1">
+            <label className="text-xs font-medium text-slate-600">API URL</label>
+            <input
+              placeholder={forge === "gitlab" ? "https://gitlab.com/api/v4" : "https://api.github.com"}
+              className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+              value={forgeUrl}
+              onChange={(e) => setForgeUrl(e.target.value)}
+            />
+          </div>
+          <div className="space-y-1">
+            <label className="text-xs font-medium text-slate-600">Access Token</label>
+            <input
+              type="password"
+              placeholder={forgeTokenSource ? "Leave empty to keep the current token" : forge === "gitlab" ? "GITLAB_TOKEN" : "GITHUB_TOKEN"}
+              className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+              value={forgeToken}
+              onChange={(e) => setForgeToken(e.target.value)}
+              disabled={!forge}
+            />
+            {forge && (
+              <p className="text-[10px] text-slate-500">
+                {forgeTokenSource ? `Using the token from ${forgeTokenSource}. ` : "No token set. "}
+                {forgeTokenSource === "the system keyring" && (
+                  <button onClick={handleForgetForgeToken} className="text-blue-500 hover:text-blue-700 font-medium hover:underline">Remove</button>
+                )}
+              </p>
+            )}
+          </div>
+          <label className="flex items-center gap-2 text-xs text-slate-600">
+            <input type="checkbox" checked={attachContext} onChange={(e) => setAttachContext(e.target.checked)} />
+            Attach the generated context to the release
+          </label>
+          <p className="text-[10px] text-slate-500">Publish creates or updates the release for the end tag. Saved tokens go to the system keyring; without one, the GITHUB_TOKEN or GITLAB_TOKEN environment variable is used.</p>
+        </div>
+
+        {/* Chat Notification Section */}
+        <div className="space-y-4">
+          <div className="flex items-center gap-2 text-sm font-medium text-slate-700 border-b border-slate-100 pb-2">
+            <span className="w-2 h-2 rounded-full bg-lime-500"></span>
+            Chat Notification
+          </div>
+
+          <div className="grid grid-cols-3 gap-3">
+            <div className="col-span-2 space-y-1">
+              <label className="text-xs font-medium text-slate-600">Webhook URL</label>
+              <input
+                type="password"
+                placeholder={webhookHost ? "Leave empty to keep the current URL" : "https://hooks.slack.com/services/..."}
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={webhookUrl}
+                onChange={(e) => setWebhookUrl(e.target.value)}
+              />
+              <p className="text-[10px] text-slate-500">
+                {webhookHost ? `Posting to ${webhookHost}. ` : "No webhook set. "}
+                {webhookHost && (
+                  <button onClick={handleForgetWebhook} className="text-blue-500 hover:text-blue-700 font-medium hover:underline">Remove</button>
+                )}
+              </p>
+            </div>
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Format</label>
+              <select
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={webhookFormat}
+                onChange={(e) => setWebhookFormat(e.target.value)}
+              >
+                <option value="">From URL</option>
+                <option value="slack">Slack</option>
+                <option value="teams">Teams</option>
+                <option value="generic">Generic JSON</option>
+              </select>
+            </div>
+          </div>
+          <textarea
+            rows={3}
+            placeholder="Built-in payload"
+            className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-xs font-mono focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+            value={webhookTemplate}
+            onChange={(e) => setWebhookTemplate(e.target.value)}
+          />
+          <p className="text-[10px] text-slate-500">{"Notify posts the executive summary. The URL is kept in the system keyring. Optional Handlebars template for the JSON payload; fields: {{title}}, {{summary}}, {{summary_markdown}}, {{version}}, {{start}}, {{end}}, {{url}}."}</p>
+        </div>
+
+        {/* Repository Notes Section */}
+        <div className="space-y-4">
+          <div className="flex items-center gap-2 text-sm font-medium text-slate-700 border-b border-slate-100 pb-2">
+            <span className="w-2 h-2 rounded-full bg-stone-500"></span>
+            Repository Notes
+          </div>
+
+          <div className="grid grid-cols-2 gap-3">
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Store In</label>
+              <select
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={notesStore}
+                onChange={(e) => setNotesStore(e.target.value)}
+              >
+                <option value="git-notes">Git notes</option>
+                <option value="tag">Tag message</option>
+              </select>
+            </div>
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Notes Ref</label>
+              <input
+                placeholder="release-notes"
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={notesRef}
+                onChange={(e) => setNotesRef(e.target.value)}
+              />
+            </div>
+          </div>
+          <label className="flex items-center gap-2 text-xs text-slate-600">
+            <input type="checkbox" checked={useStoredNotes} onChange={(e) => setUseStoredNotes(e.target.checked)} />
+            Use the notes stored for the start ref as context
+          </label>
+          <p className="text-[10px] text-slate-500">Store keeps the notes with the end tag. Push them yourself: git push origin refs/notes/release-notes, or git push -f origin &lt;tag&gt; for a re-created tag.</p>
+        </div>
+
+        {/* Style Examples Section */}
+        <div className="space-y-4">
+          <div className="flex items-center gap-2 text-sm font-medium text-slate-700 border-b border-slate-100 pb-2">
+            <span className="w-2 h-2 rounded-full bg-fuchsia-500"></span>
+            Style Examples
+          </div>
+
+          <div className="space-y-1">
+            <label className="text-xs font-medium text-slate-600">Source</label>
+            <input
+              placeholder="CHANGELOG.md, docs/releases or git-notes"
+              className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+              value={styleExamples}
+              onChange={(e) => setStyleExamples(e.target.value)}
+            />
+          </div>
+          <div className="grid grid-cols-2 gap-3">
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Releases</label>
+              <input
+                type="number"
+                min="0"
+                placeholder="3"
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={styleCount}
+                onChange={(e) => setStyleCount(e.target.value)}
+              />
+            </div>
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Max Characters</label>
+              <input
+                type="number"
+                min="0"
+                placeholder="6000"
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={styleMaxChars}
+                onChange={(e) => setStyleMaxChars(e.target.value)}
+              />
+            </div>
+          </div>
+          <p className="text-[10px] text-slate-500">Earlier release notes added to the context so new notes keep the same voice. Paths are relative to the repository. Templates can override these in their settings block.</p>
+        </div>
+
+        {/* Redaction Section */}
+        <div className="space-y-4">
+          <div className="flex items-center gap-2 text-sm font-medium text-slate-700 border-b border-slate-100 pb-2">
+            <span className="w-2 h-2 rounded-full bg-red-500"></span>
+            Redaction
+          </div>
+
+          <label className="flex items-center gap-2 text-xs text-slate-600">
+            <input type="checkbox" checked={redact} onChange={(e) => setRedact(e.target.checked)} />
+            Mask secrets and personal data before they reach the model
+          </label>
+          <textarea
+            rows={3}
+            placeholder={"[a-z0-9-]+\\.corp\\.example\\.com"}
+            disabled={!redact}
+            className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-xs font-mono focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all disabled:opacity-50"
+            value={redactPatterns}
+            onChange={(e) => setRedactPatterns(e.target.value)}
+          />
+          <p className="text-[10px] text-slate-500">Private keys, AWS and GCP keys, GitHub and Slack tokens, emails, IPv4 addresses and random-looking strings are always masked. Add your own regexes, one per line, e.g. for internal host names.</p>
+        </div>
+
+        {/* Context Layout Section */}
+        <div className="space-y-4">
+          <div className="flex items-center justify-between gap-2 text-sm font-medium text-slate-700 border-b border-slate-100 pb-2">
+            <div className="flex items-center gap-2">
+              <span className="w-2 h-2 rounded-full bg-violet-500"></span>
+              Context Layout
+            </div>
+            <button onClick={handleLoadDefaultLayout} className="text-xs text-blue-500 hover:text-blue-700 font-medium hover:underline">
+              Load Default
+            </button>
+          </div>
+
+          <textarea
+            rows={5}
+            placeholder="Built-in layout"
+            className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-xs font-mono focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+            value={contextTemplate}
+            onChange={(e) => setContextTemplate(e.target.value)}
+          />
+          <p className="text-[10px] text-slate-500">{"Handlebars template for the data sent to the model. Fields: {{notes}}, {{commit_log}}, {{commit_count}}, {{diff}}, {{jira_section}}, {{previous_notes_section}}, {{style_examples_section}}, {{#each issues}}, {{start}}, {{end}}. Leave empty for the built-in layout."}</p>
+        </div>
+
         {/* Jira Integration Section */}
         <div className="space-y-4">
           <div className="flex items-center gap-2 text-sm font-medium text-slate-700 border-b border-slate-100 pb-2">
@@ -86,13 +651,95 @@ export function SettingsModal({ isOpen, onClose }: SettingsModalProps) {
               <label className="text-xs font-medium text-slate-600">Personal Access Token (PAT)</label>
               <input 
                 type="password" 
-                placeholder="••••••••••••••••••••"
+                placeholder={jiraPatSource ? "Leave empty to keep the current token" : "••••••••••••••••••••"}
                 className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
                 value={jiraPat}
                 onChange={(e) => setJiraPat(e.target.value)}
               />
+              <p className="text-[10px] text-slate-500">
+                {jiraPatSource ? `Using the token from ${jiraPatSource}. ` : "No token set. "}
+                Saved tokens go to the system keyring. The GITSCRIBE_JIRA_PAT variable or a jira-pat file in the config folder (chmod 600) also work.
+                {jiraPatSource === "the system keyring" && (
+                  <button onClick={handleForgetPat} className="ml-1 text-blue-500 hover:text-blue-700 font-medium hover:underline">Remove</button>
+                )}
+              </p>
+            </div>
+
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Issue Cache Lifetime (minutes)</label>
+              <div className="flex items-center gap-2">
+                <input 
+                  type="number" 
+                  min="0"
+                  className="w-24 px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                  value={jiraCacheMinutes}
+                  onChange={(e) => setJiraCacheMinutes(e.target.value)}
+                />
+                <button 
+                  onClick={handleClearCache}
+                  className="px-3 py-2 text-xs font-medium text-slate-600 bg-white border border-slate-300 rounded-md hover:bg-slate-50 transition-colors"
+                >
+                  Clear Cache
+                </button>
+              </div>
+              {cacheMessage && <p className="text-[10px] text-slate-500">{cacheMessage}</p>}
+            </div>
+
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Write-Back Label</label>
+              <input
+                placeholder="released-2.4"
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={jiraWriteLabel}
+                onChange={(e) => setJiraWriteLabel(e.target.value)}
+              />
+            </div>
+            <label className="flex items-center gap-2 text-xs text-slate-600">
+              <input type="checkbox" checked={jiraWriteComment} onChange={(e) => setJiraWriteComment(e.target.checked)} />
+              Comment on each issue with the release it shipped in
+            </label>
+            <p className="text-[10px] text-slate-500">Used by the Jira button, which sets the fix version's description to the notes' summary after showing the planned changes. Needs a token with write access.</p>
+          </div>
+        </div>
+
+        {/* Confluence Section */}
+        <div className="space-y-4">
+          <div className="flex items-center gap-2 text-sm font-medium text-slate-700 border-b border-slate-100 pb-2">
+            <span className="w-2 h-2 rounded-full bg-sky-500"></span>
+            Confluence Publishing
+          </div>
+
+          <div className="space-y-1">
+            <label className="text-xs font-medium text-slate-600">Confluence URL</label>
+            <input
+              type="url"
+              placeholder="Same as the Jira server URL"
+              className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+              value={confluenceUrl}
+              onChange={(e) => setConfluenceUrl(e.target.value)}
+            />
+          </div>
+          <div className="grid grid-cols-2 gap-3">
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Space Key</label>
+              <input
+                placeholder="REL"
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={confluenceSpace}
+                onChange={(e) => setConfluenceSpace(e.target.value)}
+              />
+            </div>
+            <div className="space-y-1">
+              <label className="text-xs font-medium text-slate-600">Parent Page ID</label>
+              <input
+                placeholder="123456"
+                className="w-full px-3 py-2 bg-white border border-slate-300 rounded-md text-sm focus:outline-none focus:ring-2 focus:ring-blue-500/20 focus:border-blue-500 transition-all"
+                value={confluenceParent}
+                onChange={(e) => setConfluenceParent(e.target.value)}
+              />
             </div>
           </div>
+          <p className="text-[10px] text-slate-500">Uses the Jira token above. The page is titled after the notes' first heading and updated in place on later runs.</p>
         </div>
 
         {/* Footer */}
@@ -1,24 +1,59 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use clap::Parser;
-use gitscribe_core::jira::JiraConfig;
-use gitscribe_core::{call_ollama, generate_context, read_file_content};
+use gitscribe_core::cache::{self, CachedGeneration, GenerationCache, JiraCache};
+use gitscribe_core::chat::ChatSession;
+use gitscribe_core::confluence::{self, PageTarget};
+use gitscribe_core::credentials::{self, CredentialSource};
+use gitscribe_core::git_notes::{self, NotesStore};
+use gitscribe_core::grounding;
+use gitscribe_core::jira::{self, JiraConfig, WriteBack};
+use gitscribe_core::notify::{self, Announcement, WebhookFormat};
+use gitscribe_core::prompt::{self, PromptVars};
+use gitscribe_core::publish::{self, Attachment, ForgeConfig, ForgeKind, ReleaseDraft};
+use gitscribe_core::redact::Redactor;
+use gitscribe_core::render;
+use gitscribe_core::report::GenerationReport;
+use gitscribe_core::structured::{self, NotesFormat, ReleaseNotes};
+use gitscribe_core::style::{self, StyleSettings};
+use gitscribe_core::templates;
+use gitscribe_core::translate::{self, Glossary};
+use gitscribe_core::validate::{self, ValidationRules};
+use gitscribe_core::{
+    call_ollama, generate_context_with_stats, read_file_content, CancellationToken, OllamaOptions,
+    PathFilter,
+};
 use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Start Commit Hash
-    #[arg(long)]
-    start: String,
+    #[arg(long, required_unless_present_any = ["list_templates", "clear_jira_cache"])]
+    start: Option<String>,
 
     /// End Commit Hash
-    #[arg(long)]
-    end: String,
+    #[arg(long, required_unless_present_any = ["list_templates", "clear_jira_cache"])]
+    end: Option<String>,
 
     /// Path to Adhoc Notes Markdown file
     #[arg(long)]
     notes: Option<String>,
 
+    /// Limit the history and diff to this path (repeatable).
+    /// Prefix with '!' to exclude a path instead, e.g. --path src/ --path '!src/tests'
+    #[arg(long = "path", value_name = "PATH")]
+    paths: Vec<String>,
+
+    /// Handlebars template for the context layout (default: built-in layout).
+    /// Fields: notes, commit_log, commit_count, diff, issues, jira_section,
+    /// previous_notes_section, style_examples_section, start, end
+    #[arg(long, value_name = "FILE")]
+    context_template: Option<String>,
+
     /// Output filename (default: release_context.md or release_notes.md if ollama is used)
     #[arg(long)]
     output: Option<String>,
@@ -28,6 +63,40 @@ struct Args {
     #[arg(long)]
     system_prompt: Option<String>,
 
+    /// Use a built-in or user prompt template by name instead of --system-prompt
+    /// (default with --ollama-model: end-user). Repeat to generate notes for
+    /// several audiences from one context, e.g. --template end-user --template admin
+    #[arg(
+        long = "template",
+        value_name = "NAME",
+        conflicts_with = "system_prompt"
+    )]
+    templates: Vec<String>,
+
+    /// List available prompt templates and exit
+    #[arg(long)]
+    list_templates: bool,
+
+    /// Product name for {{product_name}} in the system prompt
+    #[arg(long)]
+    product_name: Option<String>,
+
+    /// Version for {{version}} in the system prompt (default: tag on --end, if any)
+    #[arg(long)]
+    release_version: Option<String>,
+
+    /// Release date for {{release_date}} in the system prompt (default: date of --end)
+    #[arg(long)]
+    release_date: Option<String>,
+
+    /// Target audience for {{audience}} in the system prompt
+    #[arg(long)]
+    audience: Option<String>,
+
+    /// Extra system prompt variable (repeatable), e.g. --var team=Payments
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = prompt::parse_var)]
+    vars: Vec<(String, String)>,
+
     /// Ollama Model name (e.g., "llama3", "mistral").
     /// If provided, the tool will generate release notes using this model.
     #[arg(long)]
@@ -37,83 +106,1171 @@ struct Args {
     #[arg(long, default_value = "http://localhost:11434/api/generate")]
     ollama_url: String,
 
+    /// Sampling temperature (0 for the most deterministic output)
+    #[arg(long)]
+    temperature: Option<f32>,
+
+    /// Nucleus sampling threshold
+    #[arg(long)]
+    top_p: Option<f32>,
+
+    /// Context window size in tokens
+    #[arg(long)]
+    num_ctx: Option<u32>,
+
+    /// Random seed; combine with --temperature 0 for reproducible notes
+    #[arg(long)]
+    seed: Option<i64>,
+
+    /// Penalty for repeated tokens
+    #[arg(long)]
+    repeat_penalty: Option<f32>,
+
+    /// How long Ollama keeps the model loaded afterwards (e.g. "5m", "0")
+    #[arg(long)]
+    keep_alive: Option<String>,
+
+    /// Ask the model for JSON matching the release-notes schema, validate it
+    /// and render it with --output-format (requires --ollama-model)
+    #[arg(long)]
+    structured: bool,
+
+    /// Format of the notes: markdown, html, asciidoc, rst, text or json
+    /// (json requires --structured)
+    #[arg(long, default_value = "markdown", value_parser = NotesFormat::from_str)]
+    output_format: NotesFormat,
+
+    /// Stylesheet embedded in HTML notes instead of the default theme
+    #[arg(long, value_name = "FILE")]
+    css: Option<String>,
+
+    /// Also write the notes translated into this locale (repeatable),
+    /// e.g. --translate de --translate ja (requires --ollama-model)
+    #[arg(long = "translate", value_name = "LOCALE")]
+    locales: Vec<String>,
+
+    /// File of product terms to keep untranslated, one per line
+    #[arg(long, value_name = "FILE")]
+    glossary: Option<String>,
+
+    /// How many times to re-ask the model when its JSON fails validation
+    #[arg(long, default_value_t = 2)]
+    structured_retries: usize,
+
+    /// After generating, start an interactive session to refine the notes
+    /// with follow-up instructions (requires --ollama-model)
+    #[arg(long)]
+    refine: bool,
+
+    /// Flag generated notes containing this phrase (repeatable, case-insensitive)
+    #[arg(long = "forbid", value_name = "PHRASE")]
+    forbidden_phrases: Vec<String>,
+
+    /// If the notes break the template (missing sections, placeholders,
+    /// unknown issue keys...), ask the model once to fix them
+    #[arg(long)]
+    repair: bool,
+
+    /// Write a copy of the notes annotated with the commits and issues
+    /// backing each bullet, flagging bullets nothing supports
+    #[arg(long, value_name = "FILE")]
+    review: Option<String>,
+
+    /// Write a JSON run report (sizes, token counts, timings) to this file
+    #[arg(long, value_name = "FILE")]
+    report: Option<String>,
+
+    /// Always call the model, ignoring (and not updating) cached results
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Create or update the release for the end tag on this forge:
+    /// github or gitlab (requires --ollama-model)
+    #[arg(long, value_name = "FORGE", value_parser = ForgeKind::from_str)]
+    publish: Option<ForgeKind>,
+
+    /// Repository to publish to, e.g. owner/name (default: the origin remote)
+    #[arg(long, value_name = "REPO")]
+    forge_repo: Option<String>,
+
+    /// Forge API URL (default: api.github.com or gitlab.com/api/v4)
+    #[arg(long, value_name = "URL")]
+    forge_url: Option<String>,
+
+    /// Forge access token (default: GITHUB_TOKEN or GITLAB_TOKEN)
+    #[arg(long)]
+    forge_token: Option<String>,
+
+    /// Attach the generated context to the published release
+    #[arg(long)]
+    attach_context: bool,
+
+    /// Show what would be published, written to Jira or posted without
+    /// changing anything
+    #[arg(long)]
+    dry_run: bool,
+
     /// Jira Server URL (Optional)
     #[arg(long)]
     jira_url: Option<String>,
 
-    /// Jira Personal Access Token (Optional)
+    /// Jira Personal Access Token (Optional). Visible to other users in the
+    /// process list; prefer --jira-pat-file or $GITSCRIBE_JIRA_PAT
     #[arg(long)]
     jira_pat: Option<String>,
+
+    /// File holding the Jira token, readable only by you (chmod 600).
+    /// Defaults to $GITSCRIBE_JIRA_PAT, then ~/.config/gitscribe/jira-pat
+    #[arg(long, value_name = "FILE", conflicts_with = "jira_pat")]
+    jira_pat_file: Option<String>,
+
+    /// Seconds to reuse cached Jira issues before refetching them.
+    /// Expired entries are still used when Jira cannot be reached.
+    #[arg(long, value_name = "SECONDS")]
+    jira_cache_ttl: Option<u64>,
+
+    /// Delete all cached Jira issues before fetching
+    #[arg(long)]
+    clear_jira_cache: bool,
+
+    /// Publish the notes as a page in this Confluence space, using the
+    /// Jira URL and token (requires --ollama-model)
+    #[arg(long, value_name = "KEY", requires = "confluence_parent")]
+    confluence_space: Option<String>,
+
+    /// Id of the Confluence page to create the notes under
+    #[arg(long, value_name = "PAGE_ID", requires = "confluence_space")]
+    confluence_parent: Option<String>,
+
+    /// Confluence base URL (default: --jira-url)
+    #[arg(long, value_name = "URL")]
+    confluence_url: Option<String>,
+
+    /// Page title (default: the notes' first heading, or the end ref)
+    #[arg(long, value_name = "TITLE")]
+    confluence_title: Option<String>,
+
+    /// Write back to Jira once the notes are final: set the fix version's
+    /// description to the notes' summary (requires --ollama-model)
+    #[arg(long)]
+    jira_write: bool,
+
+    /// Fix version to describe (default: --release-version or the end tag)
+    #[arg(long, value_name = "NAME", requires = "jira_write")]
+    jira_version: Option<String>,
+
+    /// Also comment on each linked issue with the release it shipped in
+    #[arg(long, requires = "jira_write")]
+    jira_comment: bool,
+
+    /// Also add this label to each linked issue
+    #[arg(long, value_name = "LABEL", requires = "jira_write")]
+    jira_label: Option<String>,
+
+    /// Post the notes' executive summary to this chat webhook
+    /// (requires --ollama-model)
+    #[arg(long, value_name = "URL")]
+    webhook: Option<String>,
+
+    /// Payload format: slack, teams or generic (default: from the URL)
+    #[arg(long, value_name = "FORMAT", value_parser = WebhookFormat::from_str, requires = "webhook")]
+    webhook_format: Option<WebhookFormat>,
+
+    /// Handlebars template for the JSON payload instead of the built-in one
+    #[arg(long, value_name = "FILE", requires = "webhook")]
+    webhook_template: Option<String>,
+
+    /// Store the final notes in the repository: in the end tag's message
+    /// (tag) or as git notes on the end commit (git-notes). Repeatable.
+    #[arg(long = "store-in", value_name = "STORE", value_parser = NotesStore::from_str)]
+    stores: Vec<NotesStore>,
+
+    /// Let --store-in tag replace the message of an existing annotated tag
+    #[arg(long, requires = "stores")]
+    overwrite_tag: bool,
+
+    /// git notes ref used by --store-in git-notes and --use-stored-notes
+    #[arg(long, value_name = "REF", default_value = git_notes::DEFAULT_NOTES_REF)]
+    notes_ref: String,
+
+    /// Add the notes stored for --start (git notes or its tag message) to
+    /// the context, so the model does not repeat them
+    #[arg(long)]
+    use_stored_notes: bool,
+
+    /// Add earlier release notes to the context as style examples: a
+    /// changelog file, a directory of notes, or git-notes[:REF] for the notes
+    /// stored on earlier tags. Templates can override this.
+    #[arg(long, value_name = "SOURCE")]
+    style_examples: Option<String>,
+
+    /// Number of earlier releases shown as style examples [default: 3]
+    #[arg(long, value_name = "N")]
+    style_count: Option<usize>,
+
+    /// Upper limit for all style examples together, in characters [default: 6000]
+    #[arg(long, value_name = "N")]
+    style_max_chars: Option<usize>,
+
+    /// Send the context to the model and output file unredacted, without
+    /// masking keys, tokens, emails, IP addresses and random-looking strings
+    #[arg(long)]
+    no_redact: bool,
+
+    /// Also redact matches of this regex, e.g. internal host names. Repeatable.
+    #[arg(
+        long = "redact-pattern",
+        value_name = "REGEX",
+        conflicts_with = "no_redact"
+    )]
+    redact_patterns: Vec<String>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
-    println!("Generating context from {} to {}...", args.start, args.end);
+    if args.list_templates {
+        println!(
+            "User templates directory: {}",
+            templates::user_templates_dir()?.display()
+        );
+        for template in templates::list_templates()? {
+            let source = if template.builtin { "built-in" } else { "user" };
+            println!("  {:<20} {}", template.name, source);
+        }
+        return Ok(());
+    }
+
+    if args.clear_jira_cache {
+        let removed = JiraCache::clear_all()?;
+        println!("Cleared {} cached Jira issue(s).", removed);
+    }
+
+    // clap enforces both unless --list-templates or --clear-jira-cache is given
+    let (Some(start), Some(end)) = (args.start.clone(), args.end.clone()) else {
+        if args.clear_jira_cache {
+            return Ok(());
+        }
+        bail!("--start and --end are required");
+    };
+
+    println!("Generating context from {} to {}...", start, end);
 
     // 1. Read Input Files
     let notes_content = read_file_content(args.notes.as_ref(), "Notes")?;
-    let system_prompt_content = read_file_content(args.system_prompt.as_ref(), "System Prompt")?;
+    let adhoc_notes = notes_content.clone();
+    let context_template = read_file_content(args.context_template.as_ref(), "Context Template")?;
+    // The model needs instructions, so AI runs fall back to the default template
+    let mut template_names = args.templates.clone();
+    if template_names.is_empty() && args.system_prompt.is_none() && args.ollama_model.is_some() {
+        template_names.push(templates::DEFAULT_TEMPLATE.to_string());
+    }
+    let multi_audience = template_names.len() > 1;
+    if multi_audience && args.refine {
+        bail!("--refine can only be used with a single --template");
+    }
+    if args.output_format == NotesFormat::Json {
+        if !args.structured {
+            bail!("--output-format json requires --structured");
+        }
+        if args.refine {
+            bail!("--refine cannot be combined with --output-format json");
+        }
+        if !args.locales.is_empty() {
+            bail!("--translate cannot be combined with --output-format json");
+        }
+    }
+    // The token is only looked up when Jira is configured
+    let jira_pat = match (&args.jira_url, &args.jira_pat) {
+        (None, _) => None,
+        (Some(_), Some(pat)) => Some((pat.clone(), CredentialSource::CommandLine)),
+        (Some(_), None) => {
+            credentials::jira_pat_from_env_or_file(args.jira_pat_file.as_deref().map(Path::new))?
+        }
+    }
+    .map(|(pat, source)| {
+        println!("Using the Jira token from {}.", source);
+        pat
+    });
+    if args.jira_url.is_some() && jira_pat.is_none() {
+        println!(
+            "No Jira token found; pass --jira-pat-file or set ${} to fetch issues.",
+            credentials::JIRA_PAT_ENV
+        );
+    }
+
+    let forge = match args.publish {
+        Some(kind) => {
+            if args.ollama_model.is_none() {
+                bail!("--publish requires --ollama-model");
+            }
+            if multi_audience {
+                bail!("--publish can only be used with a single --template");
+            }
+            if args.output_format == NotesFormat::Json {
+                bail!("--publish cannot be combined with --output-format json");
+            }
+            if !publish::tag_exists(&end, None) {
+                bail!(
+                    "--publish needs --end to be a tag, and '{}' is not one",
+                    end
+                );
+            }
+            let token = args
+                .forge_token
+                .clone()
+                .or_else(|| std::env::var(kind.token_env()).ok());
+            if token.is_none() && !args.dry_run {
+                bail!(
+                    "--publish needs --forge-token or {} (or use --dry-run)",
+                    kind.token_env()
+                );
+            }
+            let repo = match &args.forge_repo {
+                Some(repo) => repo.clone(),
+                None => publish::detect_repo(None)?,
+            };
+            Some(ForgeConfig {
+                kind,
+                api_url: args
+                    .forge_url
+                    .clone()
+                    .unwrap_or_else(|| kind.default_api_url().to_string()),
+                repo,
+                token,
+            })
+        }
+        None => None,
+    };
+    if args.confluence_space.is_some() {
+        if args.ollama_model.is_none() {
+            bail!("--confluence-space requires --ollama-model");
+        }
+        if args.jira_url.is_none() || jira_pat.is_none() {
+            bail!("Publishing to Confluence uses the Jira credentials; pass --jira-url and a Jira token");
+        }
+        if multi_audience {
+            bail!("--confluence-space can only be used with a single --template");
+        }
+        if args.output_format == NotesFormat::Json {
+            bail!("--confluence-space cannot be combined with --output-format json");
+        }
+    }
+    if args.webhook.is_some() {
+        if args.ollama_model.is_none() {
+            bail!("--webhook requires --ollama-model");
+        }
+        if multi_audience {
+            bail!("--webhook can only be used with a single --template");
+        }
+        if args.output_format == NotesFormat::Json {
+            bail!("--webhook cannot be combined with --output-format json");
+        }
+    }
+    if !args.stores.is_empty() {
+        if args.ollama_model.is_none() {
+            bail!("--store-in requires --ollama-model");
+        }
+        if multi_audience {
+            bail!("--store-in can only be used with a single --template");
+        }
+        if args.output_format == NotesFormat::Json {
+            bail!("--store-in cannot be combined with --output-format json");
+        }
+        if args.stores.contains(&NotesStore::Tag) && !publish::tag_exists(&end, None) {
+            bail!(
+                "--store-in tag needs --end to be a tag, and '{}' is not one",
+                end
+            );
+        }
+    }
+    let webhook_template = read_file_content(args.webhook_template.as_ref(), "Webhook Template")?;
+    let css = read_file_content(args.css.as_ref(), "CSS")?;
+    let glossary = match &args.glossary {
+        Some(path) => Glossary::load(Path::new(path))?,
+        None => Glossary::default(),
+    };
+
+    let base_vars = PromptVars::from_repo(&start, &end, None).merge(PromptVars {
+        product_name: args.product_name.clone(),
+        version: args.release_version.clone(),
+        release_date: args.release_date.clone(),
+        audience: args.audience.clone(),
+        extra: args.vars.iter().cloned().collect(),
+        ..Default::default()
+    });
+    let jira_version = if args.jira_write {
+        if args.ollama_model.is_none() {
+            bail!("--jira-write requires --ollama-model");
+        }
+        if args.jira_url.is_none() || jira_pat.is_none() {
+            bail!("--jira-write requires --jira-url and a Jira token");
+        }
+        if multi_audience {
+            bail!("--jira-write can only be used with a single --template");
+        }
+        match args.jira_version.clone().or(base_vars.version.clone()) {
+            Some(version) => Some(version),
+            None => bail!(
+                "--jira-write needs --jira-version (or --release-version) when --end is not a tag"
+            ),
+        }
+    } else {
+        None
+    };
+    let style_defaults = StyleSettings {
+        source: args.style_examples.clone(),
+        count: args.style_count,
+        max_chars: args.style_max_chars,
+    };
+    let render = |template: Option<&String>, audience: Option<&String>| -> Result<Option<String>> {
+        let Some(template) = template else {
+            return Ok(None);
+        };
+        let mut vars = base_vars.clone();
+        // Each audience's template name fills {{audience}} unless --audience is given
+        if vars.audience.is_none() {
+            vars.audience = audience.cloned();
+        }
+        Ok(Some(prompt::render_prompt(template, &vars)?))
+    };
+    // Render every prompt up front so a broken template fails before any fetching
+    let mut audiences = Vec::new();
+    let mut raw_templates = Vec::new();
+    if template_names.is_empty() {
+        let template = read_file_content(args.system_prompt.as_ref(), "System Prompt")?;
+        audiences.push(Audience {
+            name: None,
+            system_prompt: render(template.as_ref(), None)?,
+        });
+        raw_templates.extend(template);
+    }
+    for name in &template_names {
+        let template = templates::load_template(name)?;
+        let name = multi_audience.then(|| name.clone());
+        audiences.push(Audience {
+            system_prompt: render(Some(&template), name.as_ref())?,
+            name,
+        });
+        raw_templates.push(template);
+    }
+    let raw_templates: Vec<&str> = raw_templates.iter().map(String::as_str).collect();
+    let style_settings = style::settings_for_templates(&style_defaults, &raw_templates)?;
 
     // 2. Configure Jira
-    let jira_config = if let (Some(url), Some(pat)) = (args.jira_url, args.jira_pat) {
-        Some(JiraConfig { url, pat })
+    let jira_config = if let (Some(url), Some(pat)) = (args.jira_url.clone(), jira_pat) {
+        let cache_ttl = args
+            .jira_cache_ttl
+            .map(Duration::from_secs)
+            .unwrap_or(jira::DEFAULT_CACHE_TTL);
+        Some(JiraConfig {
+            url,
+            pat,
+            cache_ttl,
+        })
     } else {
         None
     };
 
-    // 3. Generate Core Context
-    let context =
-        generate_context(&args.start, &args.end, notes_content, None, jira_config).await?;
+    // 3. Generate Core Context (once, shared by every audience)
+    let previous_notes = if args.use_stored_notes {
+        let stored = git_notes::read_stored_notes(&start, &args.notes_ref, None);
+        match &stored {
+            Some(stored) if stored.store == NotesStore::GitNotes => {
+                println!("Using the notes stored for {} in git notes.", start)
+            }
+            Some(_) => println!("Using the notes stored in the message of tag {}.", start),
+            None => println!("No stored notes found for {}.", start),
+        }
+        stored.map(|s| s.text)
+    } else {
+        None
+    };
+    let redactor = if args.no_redact {
+        None
+    } else {
+        Some(Redactor::builtin().with_patterns(&args.redact_patterns)?)
+    };
+    let path_filter = PathFilter::from_specs(&args.paths);
+    let context_timer = Instant::now();
+    let (context, context_stats) = generate_context_with_stats(
+        &start,
+        &end,
+        notes_content,
+        &path_filter,
+        None,
+        jira_config.clone(),
+        context_template.as_deref(),
+        previous_notes.as_deref(),
+        Some(&style_settings),
+        redactor.as_ref(),
+    )
+    .await?;
+    if let Some(source) = style_settings.source.as_deref() {
+        if context_stats.style_examples.is_empty() && !source.eq_ignore_ascii_case("none") {
+            println!("No style examples found in {}.", source);
+        }
+    }
+
+    let mut base_report = GenerationReport::new(&start, &end, &context, context_stats);
+    base_report.context_ms = context_timer.elapsed().as_millis() as u64;
+    let mut reports = Vec::new();
 
     // 4. Determine Mode (Ollama vs Manual)
-    if let Some(model) = args.ollama_model {
+    if let Some(model) = &args.ollama_model {
         // --- AUTO MODE (Ollama) ---
         println!("Mode: Auto-Generate (Ollama)");
 
-        let callback = |token: &str| {
-            use std::io::{self, Write};
-            print!("{}", token);
-            let _ = io::stdout().flush();
+        let options = OllamaOptions {
+            temperature: args.temperature,
+            top_p: args.top_p,
+            num_ctx: args.num_ctx,
+            seed: args.seed,
+            repeat_penalty: args.repeat_penalty,
+            keep_alive: args.keep_alive.clone(),
+            format: args.structured.then(structured::schema),
         };
+        base_report.model = Some(model.clone());
+        base_report.options = Some(options.clone());
 
-        let final_output = call_ollama(
-            &model,
-            &args.ollama_url,
-            &context,
-            system_prompt_content.as_ref(),
-            Some(callback),
-        )
-        .await?;
+        // Ctrl-C stops the model but keeps whatever was generated so far
+        let interrupt = Interrupt::install();
+        let cancel = interrupt.begin();
+
+        let default_output = format!("release_notes.{}", args.output_format.extension());
+
+        for audience in &audiences {
+            if cancel.is_cancelled() {
+                println!("Skipping the remaining audiences.");
+                break;
+            }
+            if let Some(name) = &audience.name {
+                println!("\n=== Audience: {} ===\n", name);
+            }
+
+            let mut report = base_report.clone();
+            report.audience = audience.name.clone();
+            report.set_system_prompt(audience.system_prompt.as_deref());
+            let system_prompt_content = audience.system_prompt.as_deref();
+
+            let notes = generate_notes(
+                &args,
+                model,
+                &options,
+                &context,
+                system_prompt_content,
+                adhoc_notes.as_deref(),
+                css.as_deref(),
+                &cancel,
+                &mut report,
+            )
+            .await?;
+
+            let output_path = output_path_for(
+                args.output.as_deref(),
+                &default_output,
+                audience.name.as_deref(),
+            );
+            fs::write(&output_path, &notes.output)
+                .context(format!("Failed to write output to {}", output_path))?;
+
+            if cancel.is_cancelled() {
+                println!("\n\n--- Generation Cancelled ---");
+                println!("Partial release notes saved to: {}", output_path);
+            } else {
+                println!("\n\n--- Generation Complete ---");
+                println!("Successfully generated release notes: {}", output_path);
+                if report.violations.is_empty() {
+                    println!("Validation passed.");
+                } else {
+                    print_violations(&report.violations);
+                }
+                if let Some(grounding) = &report.grounding {
+                    let unsupported: Vec<_> = grounding.unsupported().collect();
+                    if !unsupported.is_empty() {
+                        println!(
+                            "{} bullet(s) could not be traced to a commit, issue or note:",
+                            unsupported.len()
+                        );
+                        for bullet in unsupported {
+                            println!("  line {}: {}", bullet.line, bullet.text);
+                        }
+                    }
+                    if let Some(review) = &args.review {
+                        let review_path =
+                            output_path_for(Some(review), review, audience.name.as_deref());
+                        fs::write(&review_path, grounding.annotate(&notes.markdown))
+                            .context(format!("Failed to write review to {}", review_path))?;
+                        println!("Review copy written to {}", review_path);
+                    }
+                }
+
+                let markdown = if args.refine {
+                    let session = ChatSession::new(
+                        model,
+                        &args.ollama_url,
+                        system_prompt_content,
+                        &context,
+                        OllamaOptions {
+                            format: None,
+                            ..options.clone()
+                        },
+                    )
+                    .with_draft(&notes.markdown);
+                    let refined = refine_interactively(
+                        session,
+                        &interrupt,
+                        &output_path,
+                        args.output_format,
+                        css.as_deref(),
+                    )
+                    .await?;
+                    interrupt.watch(&cancel);
+                    refined
+                } else {
+                    notes.markdown
+                };
+
+                for locale in &args.locales {
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+                    println!("\nTranslating into {}...", translate::language_name(locale));
+                    let translation = translate::translate_notes(
+                        model,
+                        &args.ollama_url,
+                        &markdown,
+                        locale,
+                        &glossary,
+                        &options,
+                        Some(&cancel),
+                    )
+                    .await?;
+                    let Some(translation) = translation else {
+                        println!("Translation cancelled.");
+                        break;
+                    };
+                    let translated_path = locale_path(&output_path, locale);
+                    let translated = render::render_markdown(
+                        &translation.text,
+                        args.output_format,
+                        css.as_deref(),
+                    )?;
+                    fs::write(&translated_path, translated)
+                        .context(format!("Failed to write output to {}", translated_path))?;
+                    println!("Translated notes written to {}", translated_path);
+                    for problem in &translation.check.problems {
+                        println!("  - {}", problem);
+                    }
+                    report.translations.push(translation.check);
+                }
+
+                if let Some(forge) = &forge {
+                    if !cancel.is_cancelled() {
+                        let attachment = args.attach_context.then(|| Attachment {
+                            file_name: "release_context.md".to_string(),
+                            content: context.clone().into_bytes(),
+                        });
+                        let draft = ReleaseDraft {
+                            tag: end.clone(),
+                            name: publish::release_title(&markdown, &end),
+                            body: markdown.clone(),
+                            attachment,
+                        };
+                        let outcome = publish::publish_release(forge, &draft, args.dry_run).await?;
+                        println!("\n{}", outcome);
+                        report.published = Some(outcome);
+                    }
+                }
 
-        println!("\n\n--- Generation Complete ---");
+                if let (Some(space), Some(parent_id), Some(jira_config)) = (
+                    &args.confluence_space,
+                    &args.confluence_parent,
+                    &jira_config,
+                ) {
+                    if !cancel.is_cancelled() {
+                        let title = args
+                            .confluence_title
+                            .clone()
+                            .unwrap_or_else(|| publish::release_title(&markdown, &end));
+                        let storage =
+                            render::to_confluence_storage(&confluence::strip_title(&markdown));
+                        if args.dry_run {
+                            println!("\n--- Confluence Storage Format ---\n{}", storage);
+                        }
+                        let target = PageTarget {
+                            url: args
+                                .confluence_url
+                                .clone()
+                                .unwrap_or_else(|| jira_config.url.clone()),
+                            space: space.clone(),
+                            parent_id: parent_id.clone(),
+                            title,
+                        };
+                        let outcome = confluence::publish_page(
+                            jira_config,
+                            &target,
+                            &storage,
+                            &confluence::version_comment(&start, &end),
+                            args.dry_run,
+                        )
+                        .await?;
+                        println!("\n{}", outcome);
+                        report.confluence = Some(outcome);
+                    }
+                }
 
-        let output_path = args
-            .output
-            .unwrap_or_else(|| "release_notes.md".to_string());
-        fs::write(&output_path, final_output)
-            .context(format!("Failed to write output to {}", output_path))?;
+                if let (Some(version), Some(jira_config)) = (&jira_version, &jira_config) {
+                    if !cancel.is_cancelled() {
+                        let comment = args
+                            .jira_comment
+                            .then(|| jira::release_comment(version, report.published_url()));
+                        let write_back = WriteBack {
+                            version: version.clone(),
+                            description: jira::version_description(&markdown),
+                            comment,
+                            label: args.jira_label.clone(),
+                        };
+                        let writes = jira::plan_writes(&report.context.issue_keys, &write_back);
+                        if args.dry_run {
+                            println!("\n--- Jira Changes (dry run) ---");
+                        } else {
+                            println!("\n--- Jira Changes ---");
+                        }
+                        let outcomes = jira::apply_writes(jira_config, writes, args.dry_run).await;
+                        for outcome in &outcomes {
+                            match (&outcome.error, &outcome.skipped) {
+                                (Some(error), _) => {
+                                    println!("  FAILED {} ({})", outcome.write, error)
+                                }
+                                (None, Some(reason)) => {
+                                    println!("  SKIPPED {} ({})", outcome.write, reason)
+                                }
+                                (None, None) => println!("  {}", outcome.write),
+                            }
+                        }
+                        report.jira_writes = outcomes;
+                    }
+                }
 
-        println!("Successfully generated release notes: {}", output_path);
+                if let Some(webhook) = &args.webhook {
+                    if !cancel.is_cancelled() {
+                        let format = args
+                            .webhook_format
+                            .unwrap_or_else(|| WebhookFormat::detect(webhook));
+                        let announcement = Announcement {
+                            version: base_vars.version.clone(),
+                            url: report.published_url().map(str::to_string),
+                            ..Announcement::from_notes(&markdown, &start, &end)
+                        };
+                        let payload = notify::render_payload(
+                            format,
+                            webhook_template.as_deref(),
+                            &announcement,
+                        )?;
+                        if args.dry_run {
+                            println!("\n--- Webhook Payload ---\n{}", payload);
+                        }
+                        let outcome = notify::send(webhook, format, &payload, args.dry_run).await?;
+                        println!("\n{}", outcome);
+                        report.notified = Some(outcome);
+                    }
+                }
+
+                if !cancel.is_cancelled() {
+                    for store in &args.stores {
+                        let outcome = git_notes::store_notes(
+                            *store,
+                            &end,
+                            &args.notes_ref,
+                            &markdown,
+                            args.overwrite_tag,
+                            args.dry_run,
+                            None,
+                        )?;
+                        println!("\n{}", outcome);
+                        report.stored.push(outcome);
+                    }
+                }
+            }
+            reports.push(report);
+        }
     } else {
         // --- MANUAL MODE ---
         println!("Mode: Context Generation (Manual)");
 
-        let final_content = if let Some(prompt) = system_prompt_content {
-            format!("{}\n\n---\n**Data to Process:**\n\n{}", prompt, context)
+        for audience in &audiences {
+            let mut report = base_report.clone();
+            report.audience = audience.name.clone();
+            report.set_system_prompt(audience.system_prompt.as_deref());
+
+            let final_content = if let Some(prompt) = &audience.system_prompt {
+                format!("{}\n\n---\n**Data to Process:**\n\n{}", prompt, context)
+            } else {
+                context.clone()
+            };
+
+            let output_path = output_path_for(
+                args.output.as_deref(),
+                "release_context.md",
+                audience.name.as_deref(),
+            );
+            fs::write(&output_path, final_content)
+                .context(format!("Failed to write output to {}", output_path))?;
+
+            println!("Successfully wrote context to {}", output_path);
+            reports.push(report);
+        }
+    }
+
+    for report in &reports {
+        println!("\n--- Run Report ---\n{}", report);
+    }
+    if let Some(report_path) = &args.report {
+        match reports.as_slice() {
+            [report] => report.write_json(report_path)?,
+            reports => GenerationReport::write_json_list(reports, report_path)?,
+        }
+        println!("Report written to {}", report_path);
+    }
+
+    Ok(())
+}
+
+/// One prompt to run against the shared context.
+struct Audience {
+    /// Template name, set only when several audiences are generated.
+    name: Option<String>,
+    system_prompt: Option<String>,
+}
+
+/// Output file for an audience: `notes.md` becomes `notes-admin.md`.
+fn output_path_for(output: Option<&str>, default: &str, audience: Option<&str>) -> String {
+    let path = output.unwrap_or(default);
+    let Some(audience) = audience else {
+        return path.to_string();
+    };
+    let path = Path::new(path);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("release_notes");
+    let file_name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}-{}.{}", stem, audience, ext),
+        None => format!("{}-{}", stem, audience),
+    };
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Output file for a translation: `notes.md` becomes `notes.de.md`.
+fn locale_path(output_path: &str, locale: &str) -> String {
+    let path = Path::new(output_path);
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("release_notes");
+    let file_name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}.{}.{}", stem, locale, ext),
+        None => format!("{}.{}", stem, locale),
+    };
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Notes from one generation, as Markdown and in the requested format.
+struct GeneratedNotes {
+    /// Used for the review copy and refinement.
+    markdown: String,
+    output: String,
+}
+
+/// Generates, validates and grounds the notes for one system prompt,
+/// reusing a cached result when there is one.
+#[allow(clippy::too_many_arguments)]
+async fn generate_notes(
+    args: &Args,
+    model: &str,
+    options: &OllamaOptions,
+    context: &str,
+    system_prompt_content: Option<&str>,
+    adhoc_notes: Option<&str>,
+    css: Option<&str>,
+    cancel: &CancellationToken,
+    report: &mut GenerationReport,
+) -> Result<GeneratedNotes> {
+    let cache = if args.no_cache {
+        None
+    } else {
+        GenerationCache::default_location().ok()
+    };
+    let cache_key =
+        GenerationCache::key(context, system_prompt_content, model, &options.sampling());
+    let system_prompt_owned = system_prompt_content.map(str::to_string);
+    let generation_timer = Instant::now();
+
+    let final_output = match cache.as_ref().and_then(|c| c.get(&cache_key)) {
+        Some(hit) => {
+            println!("Using cached result (generated at {}).", hit.created_at);
+            report.cache_hit = true;
+            hit.output
+        }
+        None => {
+            let output = if args.structured {
+                let generated = structured::generate_structured(
+                    model,
+                    &args.ollama_url,
+                    context,
+                    system_prompt_owned.as_ref(),
+                    options,
+                    args.structured_retries,
+                    Some(cancel),
+                )
+                .await?;
+                match generated {
+                    Some(generated) => {
+                        println!(
+                            "Received valid release notes after {} attempt(s).",
+                            generated.attempts
+                        );
+                        report.ollama = generated.stats;
+                        generated.raw
+                    }
+                    None => {
+                        println!("Generation cancelled.");
+                        String::new()
+                    }
+                }
+            } else {
+                let callback = |token: &str| {
+                    use std::io::{self, Write};
+                    print!("{}", token);
+                    let _ = io::stdout().flush();
+                };
+
+                let response = call_ollama(
+                    model,
+                    &args.ollama_url,
+                    context,
+                    system_prompt_owned.as_ref(),
+                    options,
+                    Some(callback),
+                    Some(cancel),
+                )
+                .await?;
+                if response.cancelled {
+                    println!("\nGeneration cancelled.");
+                }
+                report.ollama = response.stats;
+                response.text
+            };
+
+            if !cancel.is_cancelled() {
+                if let Some(cache) = &cache {
+                    let entry = CachedGeneration {
+                        start: report.start.clone(),
+                        end: report.end.clone(),
+                        model: model.to_string(),
+                        context: context.to_string(),
+                        output: output.clone(),
+                        created_at: cache::now_secs(),
+                    };
+                    if let Err(e) = cache.put(&cache_key, &entry) {
+                        eprintln!("Warning: Failed to cache result: {}", e);
+                    }
+                }
+            }
+            output
+        }
+    };
+
+    report.generation_ms = generation_timer.elapsed().as_millis() as u64;
+    report.cancelled = cancel.is_cancelled();
+
+    if cancel.is_cancelled() {
+        // Keep the partial text as it came from the model
+        return Ok(GeneratedNotes {
+            markdown: final_output.clone(),
+            output: final_output,
+        });
+    }
+
+    // Structured runs produce (and cache) JSON; checks and renderers work on Markdown
+    let structured_notes = if args.structured {
+        Some(ReleaseNotes::parse(&final_output)?)
+    } else {
+        None
+    };
+    if let (Some(notes), NotesFormat::Json) = (&structured_notes, args.output_format) {
+        // JSON has no Markdown headings to check
+        return Ok(GeneratedNotes {
+            markdown: notes.to_markdown(),
+            output: notes.render(NotesFormat::Json),
+        });
+    }
+    let mut final_output = match &structured_notes {
+        Some(notes) => notes.to_markdown(),
+        None => final_output,
+    };
+
+    let mut rules = ValidationRules::from_prompt(system_prompt_content);
+    if args.structured {
+        rules = rules.without_headings();
+    }
+    rules
+        .forbidden_phrases
+        .extend(args.forbidden_phrases.iter().cloned());
+    report.violations = rules.validate(&final_output, context);
+
+    if !report.violations.is_empty() && args.repair && args.structured {
+        // A free-form rewrite would lose the schema's structure
+        println!("Skipping the repair pass: structured notes are not rewritten.");
+    } else if !report.violations.is_empty() && args.repair {
+        print_violations(&report.violations);
+        println!("\nAsking the model to repair the notes...\n");
+        let mut session = ChatSession::new(
+            model,
+            &args.ollama_url,
+            system_prompt_content,
+            context,
+            OllamaOptions {
+                format: None,
+                ..options.clone()
+            },
+        )
+        .with_draft(&final_output);
+        let callback = |token: &str| {
+            use std::io::{self, Write};
+            print!("{}", token);
+            let _ = io::stdout().flush();
+        };
+        let instruction = validate::repair_instruction(&report.violations);
+        let response = session
+            .send(Some(&instruction), callback, Some(cancel))
+            .await?;
+        if response.cancelled {
+            println!("\nRepair cancelled; keeping the unrepaired notes.");
         } else {
-            context
+            final_output = response.text;
+            report.repaired = true;
+            report.violations = rules.validate(&final_output, context);
+        }
+    }
+
+    let sources = grounding::collect_sources(&report.context.sources, adhoc_notes);
+    report.grounding = Some(grounding::check_grounding(
+        &final_output,
+        &sources,
+        grounding::DEFAULT_SIMILARITY_THRESHOLD,
+    ));
+
+    let output = render::render_markdown(&final_output, args.output_format, css)?;
+    Ok(GeneratedNotes {
+        markdown: final_output,
+        output,
+    })
+}
+
+fn print_violations(violations: &[validate::Violation]) {
+    println!("Validation found {} problem(s):", violations.len());
+    for violation in violations {
+        println!("  - {}", violation);
+    }
+}
+
+/// Routes Ctrl-C to the running operation. The first press cancels it; a
+/// second press before the next operation starts exits.
+struct Interrupt {
+    current: Arc<Mutex<CancellationToken>>,
+}
+
+impl Interrupt {
+    fn install() -> Self {
+        let current = Arc::new(Mutex::new(CancellationToken::new()));
+        let handler = current.clone();
+        tokio::spawn(async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                let token = handler.lock().unwrap().clone();
+                if token.is_cancelled() {
+                    eprintln!("\nInterrupted.");
+                    std::process::exit(130);
+                }
+                token.cancel();
+            }
+        });
+        Self { current }
+    }
+
+    /// A fresh token for the next operation, cancelled by the next Ctrl-C.
+    fn begin(&self) -> CancellationToken {
+        let token = CancellationToken::new();
+        self.watch(&token);
+        token
+    }
+
+    /// Makes Ctrl-C cancel `token` again, e.g. after a nested operation.
+    fn watch(&self, token: &CancellationToken) {
+        *self.current.lock().unwrap() = token.clone();
+    }
+}
+
+/// Reads follow-up instructions from stdin and rewrites the output file after
+/// each revision. Returns the final draft as Markdown.
+async fn refine_interactively(
+    mut session: ChatSession,
+    interrupt: &Interrupt,
+    output_path: &str,
+    format: NotesFormat,
+    css: Option<&str>,
+) -> Result<String> {
+    use std::io::{self, BufRead, Write};
+
+    println!("\n--- Refinement ---");
+    println!("Describe a change (e.g. \"make the bug fixes section shorter\").");
+    println!("Enter an empty line, /quit or Ctrl-D to finish.");
+
+    let stdin = io::stdin();
+    // Ctrl-C at the prompt must not cancel what runs after refinement
+    interrupt.begin();
+    loop {
+        print!("\nrefine> ");
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        let instruction = line.trim();
+        if instruction.is_empty() || instruction == "/quit" {
+            break;
+        }
+
+        // Ctrl-C abandons this revision and keeps the previous draft
+        let cancel = interrupt.begin();
+
+        let callback = |token: &str| {
+            print!("{}", token);
+            let _ = io::stdout().flush();
         };
+        let response = session
+            .send(Some(instruction), callback, Some(&cancel))
+            .await?;
 
-        let output_path = args
-            .output
-            .unwrap_or_else(|| "release_context.md".to_string());
-        fs::write(&output_path, final_content)
-            .context(format!("Failed to write output to {}", output_path))?;
+        if response.cancelled {
+            println!("\n(Revision cancelled; keeping the previous draft.)");
+            continue;
+        }
 
-        println!("Successfully wrote context to {}", output_path);
+        if let Some(draft) = session.latest_draft() {
+            let draft = render::render_markdown(draft, format, css)?;
+            fs::write(output_path, draft)
+                .context(format!("Failed to write output to {}", output_path))?;
+            println!("\n\nUpdated {}", output_path);
+        }
     }
 
-    Ok(())
+    Ok(session.latest_draft().unwrap_or_default().to_string())
 }
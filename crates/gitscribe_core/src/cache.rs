@@ -0,0 +1,180 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::jira::JiraIssue;
+
+fn cache_root() -> Result<PathBuf> {
+    Ok(dirs::cache_dir()
+        .context("Could not determine the user cache directory")?
+        .join("gitscribe"))
+}
+
+/// Hex SHA-256 over length-prefixed parts, so ("ab", "c") and ("a", "bc") differ.
+fn digest(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// A previously generated set of release notes, together with the context it was built from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CachedGeneration {
+    pub start: String,
+    pub end: String,
+    pub model: String,
+    pub context: String,
+    pub output: String,
+    pub created_at: u64, // Unix seconds
+}
+
+/// On-disk store of LLM outputs, one JSON file per cache key.
+#[derive(Clone, Debug)]
+pub struct GenerationCache {
+    dir: PathBuf,
+}
+
+impl GenerationCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Uses the platform cache directory (e.g. `~/.cache/gitscribe/generations`).
+    pub fn default_location() -> Result<Self> {
+        Ok(Self::new(cache_root()?.join("generations")))
+    }
+
+    /// Hashes everything that influences the model output. Any change to the
+    /// assembled context, prompt, model or options produces a new key.
+    pub fn key(
+        context: &str,
+        system_prompt: Option<&str>,
+        model: &str,
+        options: &serde_json::Value,
+    ) -> String {
+        digest(&[
+            context,
+            system_prompt.unwrap_or(""),
+            model,
+            &options.to_string(),
+        ])
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedGeneration> {
+        let content = fs::read_to_string(self.entry_path(key)).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                eprintln!("Warning: Ignoring corrupt cache entry {}: {}", key, e);
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, key: &str, entry: &CachedGeneration) -> Result<()> {
+        fs::create_dir_all(&self.dir).context(format!(
+            "Failed to create cache directory: {}",
+            self.dir.display()
+        ))?;
+        let content = serde_json::to_string_pretty(entry)?;
+        fs::write(self.entry_path(key), content).context("Failed to write cache entry")
+    }
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedIssue {
+    fetched_at: u64, // Unix seconds
+    issue: JiraIssue,
+}
+
+/// On-disk store of fetched Jira issues, partitioned by server URL.
+///
+/// Entries older than the TTL are refetched, but are still served when the
+/// server cannot be reached so contexts can be rebuilt offline.
+#[derive(Clone, Debug)]
+pub struct JiraCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl JiraCache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self { dir, ttl }
+    }
+
+    pub fn for_server(server_url: &str, ttl: Duration) -> Result<Self> {
+        let server = digest(&[server_url.trim_end_matches('/')]);
+        Ok(Self::new(Self::root()?.join(&server[..16]), ttl))
+    }
+
+    fn root() -> Result<PathBuf> {
+        Ok(cache_root()?.join("jira"))
+    }
+
+    /// Deletes cached issues for every server. Returns the number of entries removed.
+    pub fn clear_all() -> Result<usize> {
+        let root = Self::root()?;
+        if !root.exists() {
+            return Ok(0);
+        }
+        let mut removed = 0;
+        for server_dir in fs::read_dir(&root)? {
+            let server_dir = server_dir?.path();
+            if server_dir.is_dir() {
+                removed += fs::read_dir(&server_dir)?.count();
+            }
+        }
+        fs::remove_dir_all(&root)
+            .context(format!("Failed to remove Jira cache: {}", root.display()))?;
+        Ok(removed)
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Returns the cached issue and whether it is still within the TTL.
+    pub fn get(&self, key: &str) -> Option<(JiraIssue, bool)> {
+        let content = fs::read_to_string(self.entry_path(key)).ok()?;
+        let entry: CachedIssue = serde_json::from_str(&content).ok()?;
+        let age = now_secs().saturating_sub(entry.fetched_at);
+        Some((entry.issue, age < self.ttl.as_secs()))
+    }
+
+    pub fn put(&self, issue: &JiraIssue) -> Result<()> {
+        fs::create_dir_all(&self.dir).context(format!(
+            "Failed to create cache directory: {}",
+            self.dir.display()
+        ))?;
+        let entry = CachedIssue {
+            fetched_at: now_secs(),
+            issue: issue.clone(),
+        };
+        fs::write(
+            self.entry_path(&issue.key),
+            serde_json::to_string_pretty(&entry)?,
+        )
+        .context("Failed to write Jira cache entry")
+    }
+}
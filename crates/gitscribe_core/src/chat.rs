@@ -0,0 +1,128 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+
+use crate::ollama::{self, OllamaOptions, OllamaResponse};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn new(role: &str, content: &str) -> Self {
+        Self {
+            role: role.to_string(),
+            content: content.to_string(),
+        }
+    }
+}
+
+/// Derives the `/api/chat` endpoint from either a base URL or the
+/// `/api/generate` URL the rest of the tool is configured with.
+pub fn chat_url(url: &str) -> String {
+    let base = url.trim_end_matches('/');
+    let base = base.strip_suffix("/api/generate").unwrap_or(base);
+    let base = base.strip_suffix("/api/chat").unwrap_or(base);
+    format!("{}/api/chat", base)
+}
+
+/// A refinement conversation over `/api/chat`.
+///
+/// The history starts with the system prompt and the release context, so each
+/// follow-up instruction ("shorten the bug fixes") revises the latest draft
+/// instead of regenerating from scratch.
+#[derive(Clone, Debug)]
+pub struct ChatSession {
+    pub model: String,
+    pub url: String,
+    pub options: OllamaOptions,
+    pub messages: Vec<ChatMessage>,
+}
+
+impl ChatSession {
+    pub fn new(
+        model: &str,
+        url: &str,
+        system: Option<&str>,
+        context: &str,
+        options: OllamaOptions,
+    ) -> Self {
+        let mut messages = Vec::new();
+        if let Some(system) = system {
+            messages.push(ChatMessage::new("system", system));
+        }
+        messages.push(ChatMessage::new("user", context));
+        Self {
+            model: model.to_string(),
+            url: chat_url(url),
+            options,
+            messages,
+        }
+    }
+
+    /// Seeds the conversation with a draft produced elsewhere (e.g. a cached
+    /// `/api/generate` result), as if the model had answered the context with it.
+    pub fn with_draft(mut self, draft: &str) -> Self {
+        self.messages.push(ChatMessage::new("assistant", draft));
+        self
+    }
+
+    /// The most recent assistant reply, i.e. the current version of the notes.
+    pub fn latest_draft(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|m| m.role == "assistant")
+            .map(|m| m.content.as_str())
+    }
+
+    /// Asks the model for the next draft. With `message` set it is sent as a
+    /// follow-up instruction; without it, the model answers the history as is
+    /// (used for the first draft).
+    ///
+    /// A cancelled reply is not added to the history, so the session stays on
+    /// the previous draft.
+    pub async fn send<F>(
+        &mut self,
+        message: Option<&str>,
+        callback: F,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<OllamaResponse>
+    where
+        F: Fn(&str),
+    {
+        let never_cancelled = CancellationToken::new();
+        let cancel = cancel.unwrap_or(&never_cancelled);
+
+        let mut messages = self.messages.clone();
+        if let Some(message) = message {
+            messages.push(ChatMessage::new("user", message));
+        }
+
+        let mut payload = json!({
+            "model": self.model,
+            "stream": true,
+            "messages": messages
+        });
+        self.options.apply(&mut payload);
+
+        println!(
+            "Connecting to Ollama chat ({}) with model '{}'...",
+            self.url, self.model
+        );
+
+        let Some(res) = ollama::post_json(&self.url, &payload, cancel).await? else {
+            return Ok(OllamaResponse::interrupted(String::new()));
+        };
+        let response = ollama::read_stream(res, &callback, cancel).await?;
+
+        if !response.cancelled {
+            messages.push(ChatMessage::new("assistant", &response.text));
+            self.messages = messages;
+        }
+        Ok(response)
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::{bail, Context, Result};
+use reqwest::{Client, RequestBuilder};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::jira::JiraConfig;
+use crate::publish::PublishAction;
+
+/// Where the release notes page lives.
+#[derive(Clone, Debug)]
+pub struct PageTarget {
+    /// Confluence base URL; on Jira Cloud this is usually `<jira>/wiki`.
+    pub url: String,
+    pub space: String,
+    /// Id of the page the notes are created under.
+    pub parent_id: String,
+    pub title: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PageOutcome {
+    pub action: PublishAction,
+    pub dry_run: bool,
+    pub space: String,
+    pub title: String,
+    /// Page version after publishing.
+    pub version: u64,
+    pub url: Option<String>,
+}
+
+impl fmt::Display for PageOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = match (self.dry_run, self.action) {
+            (true, PublishAction::Create) => "Would create",
+            (true, PublishAction::Update) => "Would update",
+            (false, PublishAction::Create) => "Created",
+            (false, PublishAction::Update) => "Updated",
+        };
+        write!(
+            f,
+            "{} Confluence page \"{}\" in {} (version {})",
+            verb, self.title, self.space, self.version
+        )?;
+        if let Some(url) = &self.url {
+            write!(f, ": {}", url)?;
+        }
+        Ok(())
+    }
+}
+
+/// Comment stored with each page version, e.g. "Release notes for v1.0..v1.1".
+pub fn version_comment(start: &str, end: &str) -> String {
+    format!("Release notes for {}..{}", start, end)
+}
+
+/// Drops a leading `# ` heading, which becomes the page title instead.
+pub fn strip_title(markdown: &str) -> String {
+    match markdown.trim_start().strip_prefix("# ") {
+        Some(rest) => rest
+            .split_once('\n')
+            .map_or("", |(_, body)| body)
+            .trim_start()
+            .to_string(),
+        None => markdown.to_string(),
+    }
+}
+
+fn authorized(request: RequestBuilder, config: &JiraConfig) -> RequestBuilder {
+    request
+        .header("Authorization", format!("Bearer {}", config.pat))
+        .header("Accept", "application/json")
+}
+
+fn page_url(target: &PageTarget, page: &Value) -> Option<String> {
+    let links = &page["_links"];
+    let webui = links["webui"].as_str()?;
+    let base = links["base"]
+        .as_str()
+        .unwrap_or(target.url.trim_end_matches('/'));
+    Some(format!("{}{}", base, webui))
+}
+
+async fn send(request: RequestBuilder) -> Result<Value> {
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let url = response.url().to_string();
+        let body = response.text().await.unwrap_or_default();
+        bail!("{} returned {}: {}", url, status, body.trim());
+    }
+    Ok(response.json().await?)
+}
+
+/// Creates the page titled `target.title` under the parent, or adds a new
+/// version if a page with that title already exists in the space. A dry run
+/// only looks the page up.
+pub async fn publish_page(
+    config: &JiraConfig,
+    target: &PageTarget,
+    storage: &str,
+    comment: &str,
+    dry_run: bool,
+) -> Result<PageOutcome> {
+    let client = Client::new();
+    let api = format!("{}/rest/api/content", target.url.trim_end_matches('/'));
+
+    let found = send(authorized(client.get(&api), config).query(&[
+        ("spaceKey", target.space.as_str()),
+        ("title", target.title.as_str()),
+        ("expand", "version"),
+    ]))
+    .await
+    .context(format!(
+        "Failed to look up the page in Confluence space {}",
+        target.space
+    ))?;
+    let existing = found["results"].as_array().and_then(|r| r.first()).cloned();
+
+    let current_version = existing
+        .as_ref()
+        .and_then(|page| page["version"]["number"].as_u64());
+    let mut outcome = PageOutcome {
+        action: if existing.is_some() {
+            PublishAction::Update
+        } else {
+            PublishAction::Create
+        },
+        dry_run,
+        space: target.space.clone(),
+        title: target.title.clone(),
+        version: current_version.map_or(1, |v| v + 1),
+        url: existing.as_ref().and_then(|page| page_url(target, page)),
+    };
+    if dry_run {
+        return Ok(outcome);
+    }
+
+    let mut body = json!({
+        "type": "page",
+        "title": target.title,
+        "space": { "key": target.space },
+        "ancestors": [{ "id": target.parent_id }],
+        "body": { "storage": { "value": storage, "representation": "storage" } },
+        "version": { "number": outcome.version, "message": comment },
+    });
+    let page = match &existing {
+        Some(page) => {
+            let id = page["id"].as_str().context("Confluence page has no id")?;
+            body["id"] = json!(id);
+            send(authorized(client.put(format!("{}/{}", api, id)), config).json(&body)).await?
+        }
+        None => send(authorized(client.post(&api), config).json(&body)).await?,
+    };
+    if let Some(url) = page_url(target, &page) {
+        outcome.url = Some(url);
+    }
+    Ok(outcome)
+}
@@ -0,0 +1,88 @@
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Environment variable the Jira token is read from.
+pub const JIRA_PAT_ENV: &str = "GITSCRIBE_JIRA_PAT";
+
+/// Where a token was found, for messages. Never the token itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CredentialSource {
+    CommandLine,
+    Environment(&'static str),
+    File(PathBuf),
+    Keyring,
+}
+
+impl fmt::Display for CredentialSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CommandLine => f.write_str("the command line"),
+            Self::Environment(name) => write!(f, "${}", name),
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Keyring => f.write_str("the system keyring"),
+        }
+    }
+}
+
+/// Token file read when no other source is given
+/// (`~/.config/gitscribe/jira-pat` on Linux).
+pub fn default_jira_pat_file() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("gitscribe").join("jira-pat"))
+}
+
+/// Reads a token from a file. On Unix the file must not be accessible to
+/// group or others, like an SSH key.
+pub fn read_secret_file(path: &Path) -> Result<String> {
+    let metadata =
+        fs::metadata(path).context(format!("Failed to read token file {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode();
+        if mode & 0o077 != 0 {
+            bail!(
+                "Token file {} is accessible to other users (mode {:o}); restrict it with chmod 600",
+                path.display(),
+                mode & 0o777
+            );
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = metadata;
+    let token = fs::read_to_string(path)
+        .context(format!("Failed to read token file {}", path.display()))?;
+    let token = token.trim();
+    if token.is_empty() {
+        bail!("Token file {} is empty", path.display());
+    }
+    Ok(token.to_string())
+}
+
+/// The Jira token from `file`, else [`JIRA_PAT_ENV`], else the default token
+/// file if it exists.
+pub fn jira_pat_from_env_or_file(
+    file: Option<&Path>,
+) -> Result<Option<(String, CredentialSource)>> {
+    if let Some(file) = file {
+        let token = read_secret_file(file)?;
+        return Ok(Some((token, CredentialSource::File(file.to_path_buf()))));
+    }
+    if let Some(token) = std::env::var(JIRA_PAT_ENV)
+        .ok()
+        .filter(|t| !t.trim().is_empty())
+    {
+        return Ok(Some((
+            token.trim().to_string(),
+            CredentialSource::Environment(JIRA_PAT_ENV),
+        )));
+    }
+    match default_jira_pat_file().filter(|path| path.is_file()) {
+        Some(path) => {
+            let token = read_secret_file(&path)?;
+            Ok(Some((token, CredentialSource::File(path))))
+        }
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,432 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::publish::tag_exists;
+use crate::{run_git_command, run_git_command_with_input};
+
+/// `git notes` ref used when none is given, i.e. `refs/notes/release-notes`.
+pub const DEFAULT_NOTES_REF: &str = "release-notes";
+
+/// Where release notes are kept in the repository.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotesStore {
+    /// The message of the release's annotated tag.
+    Tag,
+    /// A `git notes` entry on the release commit.
+    GitNotes,
+}
+
+impl FromStr for NotesStore {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "tag" => Ok(Self::Tag),
+            "git-notes" | "notes" => Ok(Self::GitNotes),
+            other => bail!("Unknown store '{}' (expected tag or git-notes)", other),
+        }
+    }
+}
+
+/// Full ref of a `git notes` ref given as `release-notes` or `refs/notes/...`.
+pub fn full_notes_ref(notes_ref: &str) -> String {
+    if notes_ref.starts_with("refs/") {
+        notes_ref.to_string()
+    } else {
+        format!("refs/notes/{}", notes_ref)
+    }
+}
+
+/// Notes belong to the commit, not to an annotated tag object pointing at it,
+/// so they survive the tag being re-created.
+fn commit_of(rev: &str) -> String {
+    format!("{}^{{commit}}", rev)
+}
+
+/// The notes stored for `rev` under `notes_ref`, if any.
+pub fn read_git_notes(rev: &str, notes_ref: &str, cwd: Option<&Path>) -> Option<String> {
+    let ref_arg = format!("--ref={}", full_notes_ref(notes_ref));
+    run_git_command(&["notes", &ref_arg, "show", &commit_of(rev)], cwd)
+        .ok()
+        .filter(|notes| !notes.trim().is_empty())
+}
+
+fn is_annotated(tag: &str, cwd: Option<&Path>) -> bool {
+    let tag_ref = format!("refs/tags/{}", tag);
+    run_git_command(&["cat-file", "-t", &tag_ref], cwd).is_ok_and(|kind| kind == "tag")
+}
+
+/// The signature block at the end of annotated tag `tag`, if it is signed.
+/// Git finds it the way it verifies tags, so a message that merely quotes a
+/// `-----BEGIN ...` line is not mistaken for one.
+fn tag_signature(tag: &str, cwd: Option<&Path>) -> Option<String> {
+    let tag_ref = format!("refs/tags/{}", tag);
+    run_git_command(
+        &["for-each-ref", "--format=%(contents:signature)", &tag_ref],
+        cwd,
+    )
+    .ok()
+    .filter(|signature| !signature.trim().is_empty())
+}
+
+/// The message of annotated tag `tag`, without any signature. `None` for
+/// lightweight tags and other refs.
+pub fn read_tag_message(tag: &str, cwd: Option<&Path>) -> Option<String> {
+    if !is_annotated(tag, cwd) {
+        return None;
+    }
+    let tag_ref = format!("refs/tags/{}", tag);
+    let contents =
+        run_git_command(&["for-each-ref", "--format=%(contents)", &tag_ref], cwd).ok()?;
+    let message = match tag_signature(tag, cwd) {
+        Some(signature) => contents
+            .trim_end()
+            .strip_suffix(signature.trim_end())
+            .unwrap_or(&contents),
+        None => &contents,
+    };
+    Some(message.trim().to_string()).filter(|m| !m.is_empty())
+}
+
+/// Notes found in the repository for a release.
+#[derive(Clone, Debug, Serialize)]
+pub struct StoredNotes {
+    pub store: NotesStore,
+    pub text: String,
+}
+
+/// The notes stored for `rev`: its `git notes` entry, else the message of
+/// the annotated tag of that name. One-line tag messages such as
+/// "Release 1.2" are not notes and are skipped.
+pub fn read_stored_notes(rev: &str, notes_ref: &str, cwd: Option<&Path>) -> Option<StoredNotes> {
+    if let Some(text) = read_git_notes(rev, notes_ref, cwd) {
+        return Some(StoredNotes {
+            store: NotesStore::GitNotes,
+            text,
+        });
+    }
+    read_tag_message(rev, cwd)
+        .filter(|text| text.lines().filter(|l| !l.trim().is_empty()).count() > 1)
+        .map(|text| StoredNotes {
+            store: NotesStore::Tag,
+            text,
+        })
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StoreOutcome {
+    pub store: NotesStore,
+    pub dry_run: bool,
+    pub rev: String,
+    /// Full `git notes` ref, for [`NotesStore::GitNotes`].
+    pub notes_ref: Option<String>,
+    /// Whether notes were already stored there and got replaced.
+    pub replaced: bool,
+}
+
+impl fmt::Display for StoreOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = match (self.dry_run, self.replaced) {
+            (true, true) => "Would replace",
+            (true, false) => "Would store",
+            (false, true) => "Replaced",
+            (false, false) => "Stored",
+        };
+        match (&self.store, &self.notes_ref) {
+            (NotesStore::GitNotes, Some(notes_ref)) => write!(
+                f,
+                "{} the notes on {} in git notes ({})",
+                verb, self.rev, notes_ref
+            ),
+            _ => write!(f, "{} the notes in the message of tag {}", verb, self.rev),
+        }
+    }
+}
+
+/// Writes `notes` into the annotated tag `end` or its `git notes` entry.
+/// The tag is re-created at the same commit with the notes as its message.
+/// The message of an existing annotated tag is only replaced with
+/// `overwrite_tag`; signed tags are left alone.
+pub fn store_notes(
+    store: NotesStore,
+    end: &str,
+    notes_ref: &str,
+    notes: &str,
+    overwrite_tag: bool,
+    dry_run: bool,
+    cwd: Option<&Path>,
+) -> Result<StoreOutcome> {
+    let mut outcome = StoreOutcome {
+        store,
+        dry_run,
+        rev: end.to_string(),
+        notes_ref: None,
+        replaced: false,
+    };
+    match store {
+        NotesStore::Tag => {
+            if !tag_exists(end, cwd) {
+                bail!("Cannot store the notes in tag '{}': it is not a tag", end);
+            }
+            let tag_ref = format!("refs/tags/{}", end);
+            if is_annotated(end, cwd) {
+                if tag_signature(end, cwd).is_some() {
+                    bail!(
+                        "Tag '{}' is signed; store the notes in git notes instead",
+                        end
+                    );
+                }
+                if !overwrite_tag {
+                    bail!(
+                        "Tag '{}' already has a message; allow overwriting it or store the notes in git notes instead",
+                        end
+                    );
+                }
+                outcome.replaced = true;
+            }
+            if !dry_run {
+                let commit = commit_of(&tag_ref);
+                // Verbatim, so Markdown headings are not dropped as comments
+                run_git_command_with_input(
+                    &[
+                        "tag",
+                        "-a",
+                        "-f",
+                        "--cleanup=verbatim",
+                        "-F",
+                        "-",
+                        end,
+                        &commit,
+                    ],
+                    notes,
+                    cwd,
+                )?;
+            }
+        }
+        NotesStore::GitNotes => {
+            let notes_ref = full_notes_ref(notes_ref);
+            outcome.replaced = read_git_notes(end, &notes_ref, cwd).is_some();
+            if !dry_run {
+                let ref_arg = format!("--ref={}", notes_ref);
+                run_git_command_with_input(
+                    &["notes", &ref_arg, "add", "-f", "-F", "-", &commit_of(end)],
+                    notes,
+                    cwd,
+                )?;
+            }
+            outcome.notes_ref = Some(notes_ref);
+        }
+    }
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// A throwaway repository with one commit, a lightweight tag `v1.0` and
+    /// an annotated tag `v2.0`.
+    struct TempRepo(PathBuf);
+
+    impl TempRepo {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("gitscribe-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let repo = Self(dir);
+            repo.git(&["init", "-q"]);
+            repo.git(&["config", "user.name", "Test"]);
+            repo.git(&["config", "user.email", "test@example.com"]);
+            repo.git(&["config", "tag.gpgSign", "false"]);
+            repo.git(&["commit", "-q", "--allow-empty", "-m", "Initial commit"]);
+            repo.git(&["tag", "v1.0"]);
+            repo.git(&["tag", "-a", "v2.0", "-m", "Release 2.0"]);
+            repo
+        }
+
+        fn git(&self, args: &[&str]) -> String {
+            run_git_command(args, Some(&self.0)).unwrap()
+        }
+
+        fn path(&self) -> Option<&Path> {
+            Some(&self.0)
+        }
+    }
+
+    impl Drop for TempRepo {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const NOTES: &str = "# Release\n\n## Fixes\n\n- Export works\n";
+
+    #[test]
+    fn stores_in_lightweight_tag_and_reads_back() {
+        let repo = TempRepo::new("lightweight-tag");
+        let commit = repo.git(&["rev-parse", "v1.0"]);
+
+        let outcome = store_notes(
+            NotesStore::Tag,
+            "v1.0",
+            DEFAULT_NOTES_REF,
+            NOTES,
+            false,
+            false,
+            repo.path(),
+        )
+        .unwrap();
+        assert!(!outcome.replaced);
+        assert_eq!(repo.git(&["cat-file", "-t", "refs/tags/v1.0"]), "tag");
+        assert_eq!(repo.git(&["rev-parse", "v1.0^{commit}"]), commit);
+
+        let stored = read_stored_notes("v1.0", DEFAULT_NOTES_REF, repo.path()).unwrap();
+        assert_eq!(stored.store, NotesStore::Tag);
+        assert_eq!(stored.text, NOTES.trim());
+    }
+
+    #[test]
+    fn annotated_tag_needs_overwrite() {
+        let repo = TempRepo::new("annotated-tag");
+
+        let err = store_notes(
+            NotesStore::Tag,
+            "v2.0",
+            DEFAULT_NOTES_REF,
+            NOTES,
+            false,
+            false,
+            repo.path(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("already has a message"));
+        assert_eq!(
+            read_tag_message("v2.0", repo.path()).as_deref(),
+            Some("Release 2.0")
+        );
+
+        let dry_run = store_notes(
+            NotesStore::Tag,
+            "v2.0",
+            DEFAULT_NOTES_REF,
+            NOTES,
+            true,
+            true,
+            repo.path(),
+        )
+        .unwrap();
+        assert!(dry_run.replaced);
+        assert_eq!(
+            read_tag_message("v2.0", repo.path()).as_deref(),
+            Some("Release 2.0")
+        );
+
+        let outcome = store_notes(
+            NotesStore::Tag,
+            "v2.0",
+            DEFAULT_NOTES_REF,
+            NOTES,
+            true,
+            false,
+            repo.path(),
+        )
+        .unwrap();
+        assert!(outcome.replaced);
+        assert_eq!(
+            read_tag_message("v2.0", repo.path()).as_deref(),
+            Some(NOTES.trim())
+        );
+    }
+
+    #[test]
+    fn quoted_armor_is_not_a_signature() {
+        let repo = TempRepo::new("quoted-armor");
+        let notes = "# Release\n\n- Ship the new CA:\n\n```\n-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n```\n";
+
+        store_notes(
+            NotesStore::Tag,
+            "v1.0",
+            DEFAULT_NOTES_REF,
+            notes,
+            false,
+            false,
+            repo.path(),
+        )
+        .unwrap();
+        assert_eq!(
+            read_tag_message("v1.0", repo.path()).as_deref(),
+            Some(notes.trim())
+        );
+        // Replacing them again is not refused as a signed tag
+        store_notes(
+            NotesStore::Tag,
+            "v1.0",
+            DEFAULT_NOTES_REF,
+            NOTES,
+            true,
+            false,
+            repo.path(),
+        )
+        .unwrap();
+        assert_eq!(
+            read_tag_message("v1.0", repo.path()).as_deref(),
+            Some(NOTES.trim())
+        );
+    }
+
+    #[test]
+    fn stores_in_git_notes_and_reads_back() {
+        let repo = TempRepo::new("git-notes");
+
+        let outcome = store_notes(
+            NotesStore::GitNotes,
+            "v2.0",
+            DEFAULT_NOTES_REF,
+            NOTES,
+            false,
+            false,
+            repo.path(),
+        )
+        .unwrap();
+        assert!(!outcome.replaced);
+        assert_eq!(
+            outcome.notes_ref.as_deref(),
+            Some("refs/notes/release-notes")
+        );
+
+        let stored = read_stored_notes("v2.0", DEFAULT_NOTES_REF, repo.path()).unwrap();
+        assert_eq!(stored.store, NotesStore::GitNotes);
+        assert_eq!(stored.text, NOTES.trim());
+        // Stored on the commit, so the lightweight tag of the same commit sees them too
+        assert!(read_git_notes("v1.0", DEFAULT_NOTES_REF, repo.path()).is_some());
+
+        let again = store_notes(
+            NotesStore::GitNotes,
+            "v2.0",
+            DEFAULT_NOTES_REF,
+            "- Other\n",
+            false,
+            false,
+            repo.path(),
+        )
+        .unwrap();
+        assert!(again.replaced);
+        assert_eq!(
+            read_git_notes("v2.0", DEFAULT_NOTES_REF, repo.path()).as_deref(),
+            Some("- Other")
+        );
+        // The tag itself is untouched
+        assert_eq!(
+            read_tag_message("v2.0", repo.path()).as_deref(),
+            Some("Release 2.0")
+        );
+    }
+}
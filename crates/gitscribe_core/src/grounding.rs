@@ -0,0 +1,277 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::jira::extract_issue_keys;
+use crate::layout::ContextData;
+
+/// Share of a bullet's distinctive words that must appear in a source for it
+/// to count as support when no issue key links the two.
+pub const DEFAULT_SIMILARITY_THRESHOLD: f64 = 0.35;
+
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "with", "that", "this", "you", "your", "now", "can", "are", "was", "were",
+    "has", "have", "from", "into", "when", "will", "more", "some", "not", "but", "all", "its",
+    "also", "been", "which", "their", "them", "they", "than", "then", "where", "while", "issue",
+    "fix", "fixed", "fixes", "add", "added", "adds", "update", "updated", "new", "use", "make",
+    "makes", "easier", "improved", "improve", "release",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    Commit,
+    Issue,
+    Note,
+}
+
+/// Something a bullet may legitimately be based on.
+#[derive(Clone, Debug, Serialize)]
+pub struct Source {
+    pub kind: SourceKind,
+    /// Issue key for Jira issues, `None` for commits and notes.
+    pub key: Option<String>,
+    /// Commit subject, issue summary or note line.
+    pub text: String,
+    /// Issue description and comments; matched against but not reported.
+    #[serde(skip)]
+    pub detail: String,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.kind, &self.key) {
+            (SourceKind::Issue, Some(key)) => write!(f, "{} {}", key, self.text),
+            (SourceKind::Commit, _) => write!(f, "commit \"{}\"", self.text),
+            _ => write!(f, "notes \"{}\"", self.text),
+        }
+    }
+}
+
+/// Why a source was linked to a bullet.
+#[derive(Clone, Debug, Serialize)]
+pub struct Evidence {
+    pub source: Source,
+    /// The issue key or PR number both mention, if that is what linked them.
+    pub reference: Option<String>,
+    pub similarity: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BulletCheck {
+    /// 1-based line number in the notes.
+    pub line: usize,
+    pub text: String,
+    pub evidence: Vec<Evidence>,
+}
+
+impl BulletCheck {
+    pub fn is_supported(&self) -> bool {
+        !self.evidence.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct GroundingReport {
+    pub bullets: Vec<BulletCheck>,
+}
+
+impl GroundingReport {
+    pub fn unsupported(&self) -> impl Iterator<Item = &BulletCheck> {
+        self.bullets.iter().filter(|b| !b.is_supported())
+    }
+
+    pub fn supported_count(&self) -> usize {
+        self.bullets.iter().filter(|b| b.is_supported()).count()
+    }
+
+    /// The notes with a review line under every bullet, naming its sources or
+    /// marking it as unsupported.
+    pub fn annotate(&self, notes: &str) -> String {
+        let mut out = String::new();
+        for (i, line) in notes.lines().enumerate() {
+            out.push_str(line);
+            out.push('\n');
+            let Some(check) = self.bullets.iter().find(|b| b.line == i + 1) else {
+                continue;
+            };
+            let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+            if check.is_supported() {
+                let sources: Vec<String> = check
+                    .evidence
+                    .iter()
+                    .take(3)
+                    .map(|e| e.source.to_string())
+                    .collect();
+                out.push_str(&format!(
+                    "{}  > ✔ Supported by: {}\n",
+                    indent,
+                    sources.join("; ")
+                ));
+            } else {
+                out.push_str(&format!(
+                    "{}  > ⚠ UNSUPPORTED: no matching commit, issue or note\n",
+                    indent
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// The commits and Jira issues that went into a context, read from its data
+/// rather than the rendered text so custom layouts are covered too.
+pub fn context_sources(data: &ContextData) -> Vec<Source> {
+    // `git log --pretty=format:- [%ai] %s`
+    let commit = Regex::new(r"^- \[\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2} [+-]\d{4}\] (.+)$").unwrap();
+
+    let commits = data.commit_log.lines().filter_map(|line| {
+        let caps = commit.captures(line)?;
+        Some(Source {
+            kind: SourceKind::Commit,
+            key: None,
+            text: caps[1].trim().to_string(),
+            detail: String::new(),
+        })
+    });
+    let issues = data.issues.iter().map(|issue| {
+        let mut detail = issue.description.clone();
+        for comment in &issue.comments {
+            detail.push('\n');
+            detail.push_str(&comment.body);
+        }
+        Source {
+            kind: SourceKind::Issue,
+            key: Some(issue.key.clone()),
+            text: issue.summary.trim().to_string(),
+            detail,
+        }
+    });
+    commits.chain(issues).collect()
+}
+
+/// The context's sources plus one source per line of the adhoc notes.
+pub fn collect_sources(context_sources: &[Source], notes: Option<&str>) -> Vec<Source> {
+    let mut sources = context_sources.to_vec();
+    if let Some(notes) = notes {
+        sources.extend(
+            notes
+                .lines()
+                .map(|l| l.trim().trim_start_matches(['-', '*']).trim())
+                .filter(|l| !l.is_empty())
+                .map(|l| Source {
+                    kind: SourceKind::Note,
+                    key: None,
+                    text: l.to_string(),
+                    detail: String::new(),
+                }),
+        );
+    }
+    sources
+}
+
+fn references(text: &str) -> HashSet<String> {
+    let pr = Regex::new(r"#(\d+)\b").unwrap();
+    let mut refs: HashSet<String> = extract_issue_keys(text)
+        .into_iter()
+        .filter(|k| !k.starts_with("CVE-"))
+        .collect();
+    refs.extend(pr.captures_iter(text).map(|c| format!("#{}", &c[1])));
+    refs
+}
+
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(|w| w.to_lowercase())
+        .filter(|w| w.len() >= 3 && !STOPWORDS.contains(&w.as_str()))
+        .map(|w| {
+            // Crude stemming so "exports" matches "export" and "crashing" matches "crash"
+            for suffix in ["ing", "ed", "es", "s"] {
+                if w.len() > suffix.len() + 3 {
+                    if let Some(stem) = w.strip_suffix(suffix) {
+                        return stem.to_string();
+                    }
+                }
+            }
+            w
+        })
+        .collect()
+}
+
+/// Fraction of the bullet's words found in the source.
+fn similarity(bullet: &HashSet<String>, source: &HashSet<String>) -> f64 {
+    if bullet.is_empty() {
+        return 0.0;
+    }
+    bullet.intersection(source).count() as f64 / bullet.len() as f64
+}
+
+/// Bullet items of Markdown notes, skipping "None in this release." fillers.
+fn bullets(notes: &str) -> Vec<(usize, String)> {
+    let marker = Regex::new(r"^\s*(?:[-*+]|\d+\.)\s+(.+)$").unwrap();
+    notes
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let text = marker.captures(line)?[1].trim().to_string();
+            let plain = text.trim_matches(|c: char| c == '*' || c == '"' || c == '.');
+            (!plain.eq_ignore_ascii_case("none in this release")).then_some((i + 1, text))
+        })
+        .collect()
+}
+
+/// Links every bullet in `notes_output` to the commits, issues and adhoc notes
+/// that support it, either by a shared issue key / PR number or by word overlap
+/// of at least `threshold`.
+pub fn check_grounding(notes_output: &str, sources: &[Source], threshold: f64) -> GroundingReport {
+    let source_words: Vec<HashSet<String>> = sources
+        .iter()
+        .map(|s| words(&format!("{}\n{}", s.text, s.detail)))
+        .collect();
+    let source_refs: Vec<HashSet<String>> = sources
+        .iter()
+        .map(|s| {
+            let mut refs = references(&s.text);
+            refs.extend(s.key.clone());
+            refs
+        })
+        .collect();
+
+    let bullets = bullets(notes_output)
+        .into_iter()
+        .map(|(line, text)| {
+            let bullet_words = words(&text);
+            let bullet_refs = references(&text);
+
+            let mut evidence: Vec<Evidence> = sources
+                .iter()
+                .enumerate()
+                .filter_map(|(i, source)| {
+                    let reference = bullet_refs.intersection(&source_refs[i]).next().cloned();
+                    let similarity = similarity(&bullet_words, &source_words[i]);
+                    (reference.is_some() || similarity >= threshold).then(|| Evidence {
+                        source: source.clone(),
+                        reference,
+                        similarity,
+                    })
+                })
+                .collect();
+            // Key matches first, then the closest wording
+            evidence.sort_by(|a, b| {
+                b.reference
+                    .is_some()
+                    .cmp(&a.reference.is_some())
+                    .then(b.similarity.total_cmp(&a.similarity))
+            });
+
+            BulletCheck {
+                line,
+                text,
+                evidence,
+            }
+        })
+        .collect();
+
+    GroundingReport { bullets }
+}
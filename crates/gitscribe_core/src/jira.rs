@@ -1,13 +1,21 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use regex::Regex;
-use reqwest::Client;
-use serde::Deserialize;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::cache::JiraCache;
+
+/// How long fetched issues are reused before being refetched.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
 
 #[derive(Clone, Debug)]
 pub struct JiraConfig {
     pub url: String,
     pub pat: String,
+    pub cache_ttl: Duration,
 }
 
 #[derive(Debug, Deserialize)]
@@ -48,7 +56,7 @@ struct JiraApiResponse {
     fields: JiraIssueFields,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct JiraIssue {
     pub key: String,
     pub summary: String,
@@ -74,6 +82,8 @@ pub fn extract_issue_keys(text: &str) -> Vec<String> {
     sorted_keys
 }
 
+/// Returns `Ok(None)` when Jira answers but the issue cannot be read, and an
+/// error only when the server cannot be reached.
 pub async fn fetch_issue(
     client: &Client,
     config: &JiraConfig,
@@ -82,7 +92,7 @@ pub async fn fetch_issue(
     let base_url = config.url.trim_end_matches('/');
     let url = format!("{}/rest/api/2/issue/{}", base_url, key);
 
-    println!("Fetching Jira issue: {}", key);
+    eprintln!("Fetching Jira issue: {}", key);
 
     let res = client
         .get(&url)
@@ -119,12 +129,12 @@ pub async fn fetch_issue(
                         }))
                     }
                     Err(e) => {
-                        println!("Failed to parse Jira response for {}: {}", key, e);
+                        eprintln!("Failed to parse Jira response for {}: {}", key, e);
                         Ok(None)
                     }
                 }
             } else {
-                println!(
+                eprintln!(
                     "Jira request failed for {} (Status: {})",
                     key,
                     response.status()
@@ -132,9 +142,554 @@ pub async fn fetch_issue(
                 Ok(None)
             }
         }
+        Err(e) if e.is_connect() || e.is_timeout() => Err(e.into()),
         Err(e) => {
-            println!("Jira connection error for {}: {}", key, e);
+            eprintln!("Jira connection error for {}: {}", key, e);
             Ok(None)
         }
     }
 }
+
+/// Whether `fetch_issue` failed because the server could not be reached.
+fn is_unreachable(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_connect() || e.is_timeout())
+}
+
+/// Where an issue returned by [`fetch_issue_cached`] came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IssueSource {
+    Cache,
+    Network,
+    /// Expired cache entry, used because the server could not be reached.
+    StaleCache,
+    Failed,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct FetchStats {
+    pub fetched: usize,
+    pub cached: usize,
+    pub stale: usize,
+    pub failed: usize,
+}
+
+impl FetchStats {
+    pub fn record(&mut self, source: IssueSource) {
+        match source {
+            IssueSource::Cache => self.cached += 1,
+            IssueSource::Network => self.fetched += 1,
+            IssueSource::StaleCache => self.stale += 1,
+            IssueSource::Failed => self.failed += 1,
+        }
+    }
+}
+
+impl std::fmt::Display for FetchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} fetched, {} from cache, {} stale from cache, {} failed",
+            self.fetched, self.cached, self.stale, self.failed
+        )
+    }
+}
+
+pub async fn fetch_issue_cached(
+    client: &Client,
+    config: &JiraConfig,
+    cache: Option<&JiraCache>,
+    key: &str,
+) -> (Option<JiraIssue>, IssueSource) {
+    let cached = cache.and_then(|c| c.get(key));
+    if let Some((issue, true)) = &cached {
+        return (Some(issue.clone()), IssueSource::Cache);
+    }
+
+    match fetch_issue(client, config, key).await {
+        Ok(Some(issue)) => {
+            if let Some(cache) = cache {
+                if let Err(e) = cache.put(&issue) {
+                    eprintln!("Failed to cache Jira issue {}: {}", key, e);
+                }
+            }
+            (Some(issue), IssueSource::Network)
+        }
+        Err(e) if is_unreachable(&e) => {
+            eprintln!("Jira connection error for {}: {}", key, e);
+            match cached {
+                Some((issue, _)) => (Some(issue), IssueSource::StaleCache),
+                None => (None, IssueSource::Failed),
+            }
+        }
+        // Not found, rejected or unreadable: an old copy would hide the problem
+        _ => (None, IssueSource::Failed),
+    }
+}
+
+/// Jira limits fix version descriptions to 255 characters.
+const VERSION_DESCRIPTION_LIMIT: usize = 255;
+
+/// A change made in Jira once the notes are final.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JiraWrite {
+    VersionDescription {
+        project: String,
+        version: String,
+        description: String,
+    },
+    Comment {
+        key: String,
+        body: String,
+    },
+    Label {
+        key: String,
+        label: String,
+    },
+}
+
+impl std::fmt::Display for JiraWrite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::VersionDescription {
+                project,
+                version,
+                description,
+            } => write!(
+                f,
+                "Set the description of fix version {} in {} to \"{}\"",
+                version, project, description
+            ),
+            Self::Comment { key, body } => write!(f, "Comment on {}: \"{}\"", key, body),
+            Self::Label { key, label } => write!(f, "Add label {} to {}", label, key),
+        }
+    }
+}
+
+/// A planned write and, unless it was a dry run, how it went.
+#[derive(Clone, Debug, Serialize)]
+pub struct JiraWriteOutcome {
+    #[serde(flatten)]
+    pub write: JiraWrite,
+    pub dry_run: bool,
+    pub applied: bool,
+    /// Why the write was left out, e.g. a project without the fix version.
+    pub skipped: Option<String>,
+    pub error: Option<String>,
+}
+
+/// What to write back for a release.
+#[derive(Clone, Debug, Default)]
+pub struct WriteBack {
+    /// Fix version name, e.g. `2.4.0`.
+    pub version: String,
+    /// New description of the fix version in each issue's project.
+    pub description: String,
+    /// Comment posted on each issue, if any.
+    pub comment: Option<String>,
+    /// Label added to each issue, if any.
+    pub label: Option<String>,
+}
+
+/// One fix version update per project among `issue_keys`, then the comments
+/// and labels for each issue. Projects without the version are skipped when
+/// the writes are applied.
+pub fn plan_writes(issue_keys: &[String], write_back: &WriteBack) -> Vec<JiraWrite> {
+    let mut projects: Vec<&str> = issue_keys
+        .iter()
+        .filter_map(|key| key.rsplit_once('-').map(|(project, _)| project))
+        .collect();
+    projects.sort();
+    projects.dedup();
+
+    let mut writes: Vec<JiraWrite> = projects
+        .into_iter()
+        .map(|project| JiraWrite::VersionDescription {
+            project: project.to_string(),
+            version: write_back.version.clone(),
+            description: write_back.description.clone(),
+        })
+        .collect();
+    for key in issue_keys {
+        if let Some(body) = &write_back.comment {
+            writes.push(JiraWrite::Comment {
+                key: key.clone(),
+                body: body.clone(),
+            });
+        }
+        if let Some(label) = &write_back.label {
+            writes.push(JiraWrite::Label {
+                key: key.clone(),
+                label: label.clone(),
+            });
+        }
+    }
+    writes
+}
+
+/// Comment posted on issues that shipped in `version`, linking the
+/// published notes when there are any.
+pub fn release_comment(version: &str, link: Option<&str>) -> String {
+    match link {
+        Some(url) => format!("Released in {}. Release notes: {}", version, url),
+        None => format!("Released in {}.", version),
+    }
+}
+
+/// Fix version description from the notes: their first paragraph as plain
+/// text, shortened to Jira's limit at a word boundary.
+pub fn version_description(notes: &str) -> String {
+    let paragraph: Vec<&str> = notes
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty() || line.starts_with('#'))
+        .take_while(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    let text = crate::render::to_plain_text(&paragraph.join("\n"));
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= VERSION_DESCRIPTION_LIMIT {
+        return text;
+    }
+    let mut short = String::new();
+    for word in text.split(' ') {
+        // Room for the separating space and the ellipsis
+        if short.chars().count() + word.chars().count() + 2 > VERSION_DESCRIPTION_LIMIT {
+            break;
+        }
+        if !short.is_empty() {
+            short.push(' ');
+        }
+        short.push_str(word);
+    }
+    short + "…"
+}
+
+fn write_request(request: RequestBuilder, config: &JiraConfig) -> RequestBuilder {
+    request
+        .header("Authorization", format!("Bearer {}", config.pat))
+        .header("Accept", "application/json")
+}
+
+async fn send_write(request: RequestBuilder) -> Result<Response> {
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Jira returned {}: {}", status, body.trim());
+    }
+    Ok(response)
+}
+
+#[derive(Debug, Deserialize)]
+struct JiraVersion {
+    id: String,
+    name: String,
+}
+
+/// Id of the version named `version` in `project`, or `None` if the project
+/// has no such version.
+pub async fn find_version(
+    client: &Client,
+    config: &JiraConfig,
+    project: &str,
+    version: &str,
+) -> Result<Option<String>> {
+    let base_url = config.url.trim_end_matches('/');
+    let versions: Vec<JiraVersion> = send_write(write_request(
+        client.get(format!(
+            "{}/rest/api/2/project/{}/versions",
+            base_url, project
+        )),
+        config,
+    ))
+    .await?
+    .json()
+    .await?;
+    Ok(versions
+        .into_iter()
+        .find(|v| v.name == version)
+        .map(|v| v.id))
+}
+
+/// Sets the description of the version with id `version_id`.
+pub async fn update_version_description(
+    client: &Client,
+    config: &JiraConfig,
+    version_id: &str,
+    description: &str,
+) -> Result<()> {
+    let base_url = config.url.trim_end_matches('/');
+    send_write(
+        write_request(
+            client.put(format!("{}/rest/api/2/version/{}", base_url, version_id)),
+            config,
+        )
+        .json(&json!({ "description": description })),
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn add_comment(
+    client: &Client,
+    config: &JiraConfig,
+    key: &str,
+    body: &str,
+) -> Result<()> {
+    let base_url = config.url.trim_end_matches('/');
+    send_write(
+        write_request(
+            client.post(format!("{}/rest/api/2/issue/{}/comment", base_url, key)),
+            config,
+        )
+        .json(&json!({ "body": body })),
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn add_label(client: &Client, config: &JiraConfig, key: &str, label: &str) -> Result<()> {
+    let base_url = config.url.trim_end_matches('/');
+    send_write(
+        write_request(
+            client.put(format!("{}/rest/api/2/issue/{}", base_url, key)),
+            config,
+        )
+        .json(&json!({ "update": { "labels": [{ "add": label }] } })),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Applies `writes` in order. A failed write is recorded and the rest still
+/// run. Fix versions are looked up first, even in a dry run, and projects
+/// without the version are skipped; a dry run applies nothing.
+pub async fn apply_writes(
+    config: &JiraConfig,
+    writes: Vec<JiraWrite>,
+    dry_run: bool,
+) -> Vec<JiraWriteOutcome> {
+    let client = Client::new();
+    let mut outcomes = Vec::new();
+    for write in writes {
+        let mut outcome = JiraWriteOutcome {
+            write,
+            dry_run,
+            applied: false,
+            skipped: None,
+            error: None,
+        };
+        let result = match &outcome.write {
+            JiraWrite::VersionDescription {
+                project,
+                version,
+                description,
+            } => match find_version(&client, config, project, version).await {
+                Ok(None) => {
+                    outcome.skipped = Some(format!("{} has no version named {}", project, version));
+                    Ok(())
+                }
+                Ok(Some(_)) if dry_run => Ok(()),
+                Ok(Some(id)) => update_version_description(&client, config, &id, description)
+                    .await
+                    .map(|()| outcome.applied = true),
+                Err(e) => Err(e),
+            },
+            _ if dry_run => Ok(()),
+            JiraWrite::Comment { key, body } => add_comment(&client, config, key, body)
+                .await
+                .map(|()| outcome.applied = true),
+            JiraWrite::Label { key, label } => add_label(&client, config, key, label)
+                .await
+                .map(|()| outcome.applied = true),
+        };
+        outcome.error = result.err().map(|e| e.to_string());
+        outcomes.push(outcome);
+    }
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|k| k.to_string()).collect()
+    }
+
+    fn release() -> WriteBack {
+        WriteBack {
+            version: "2.0".to_string(),
+            description: "Faster exports.".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn config(url: String) -> JiraConfig {
+        JiraConfig {
+            url,
+            pat: "secret".to_string(),
+            cache_ttl: DEFAULT_CACHE_TTL,
+        }
+    }
+
+    fn cache_dir(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("gitscribe-{}-{}", name, std::process::id()))
+    }
+
+    /// A cache holding expired copies of ABC-1 to ABC-3.
+    fn stale_cache(name: &str) -> JiraCache {
+        let _ = std::fs::remove_dir_all(cache_dir(name));
+        let cache = JiraCache::new(cache_dir(name), Duration::ZERO);
+        for key in ["ABC-1", "ABC-2", "ABC-3"] {
+            cache
+                .put(&JiraIssue {
+                    key: key.to_string(),
+                    summary: "Old summary".to_string(),
+                    description: None,
+                    status: "Done".to_string(),
+                    issue_type: "Bug".to_string(),
+                    comments: Vec::new(),
+                    updated: "2024-01-01T00:00:00.000+0000".to_string(),
+                    resolution_date: None,
+                })
+                .unwrap();
+        }
+        cache
+    }
+
+    #[tokio::test]
+    async fn serves_stale_issues_only_when_offline() {
+        let client = Client::new();
+        let cache = stale_cache("jira-offline");
+        let config = config(test_server::closed_url().await);
+        let (issue, source) = fetch_issue_cached(&client, &config, Some(&cache), "ABC-1").await;
+        assert_eq!(source, IssueSource::StaleCache);
+        assert_eq!(issue.unwrap().summary, "Old summary");
+
+        let (url, _) = test_server::serve(vec![
+            ("GET /rest/api/2/issue/ABC-1 ", 401, json!({})),
+            (
+                "GET /rest/api/2/issue/ABC-2 ",
+                200,
+                json!({ "key": "ABC-2" }),
+            ),
+        ])
+        .await;
+        let config = self::config(url);
+        let cache = stale_cache("jira-online");
+        for key in ["ABC-1", "ABC-2", "ABC-3"] {
+            let (issue, source) = fetch_issue_cached(&client, &config, Some(&cache), key).await;
+            assert_eq!(source, IssueSource::Failed, "{}", key);
+            assert!(issue.is_none());
+        }
+        for name in ["jira-offline", "jira-online"] {
+            let _ = std::fs::remove_dir_all(cache_dir(name));
+        }
+    }
+
+    #[test]
+    fn plans_one_version_write_per_project() {
+        let writes = plan_writes(&keys(&["XYZ-3", "ABC-1", "ABC-2"]), &release());
+        let plan: Vec<String> = writes.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            plan,
+            vec![
+                "Set the description of fix version 2.0 in ABC to \"Faster exports.\"",
+                "Set the description of fix version 2.0 in XYZ to \"Faster exports.\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_and_labels_are_opt_in() {
+        let write_back = WriteBack {
+            comment: Some("Released in 2.0.".to_string()),
+            label: Some("released".to_string()),
+            ..release()
+        };
+        let writes = plan_writes(&keys(&["ABC-1", "ABC-2"]), &write_back);
+        let plan: Vec<String> = writes.iter().skip(1).map(|w| w.to_string()).collect();
+        assert_eq!(
+            plan,
+            vec![
+                "Comment on ABC-1: \"Released in 2.0.\"",
+                "Add label released to ABC-1",
+                "Comment on ABC-2: \"Released in 2.0.\"",
+                "Add label released to ABC-2",
+            ]
+        );
+        assert_eq!(plan_writes(&keys(&["ABC-1", "ABC-2"]), &release()).len(), 1);
+        assert!(plan_writes(&[], &write_back).is_empty());
+    }
+
+    #[test]
+    fn version_description_uses_first_paragraph() {
+        let notes = "# Acme 2.0\n\n## Summary\nFaster **exports** and\na new `import` command.\n\n## Fixes\n- A fix\n";
+        assert_eq!(
+            version_description(notes),
+            "Faster exports and a new import command."
+        );
+    }
+
+    #[test]
+    fn version_description_is_cut_at_a_word() {
+        let notes = "word ".repeat(100);
+        let description = version_description(&notes);
+        assert!(description.chars().count() <= VERSION_DESCRIPTION_LIMIT);
+        assert!(description.ends_with("word…"), "{}", description);
+
+        let exact = "x".repeat(VERSION_DESCRIPTION_LIMIT);
+        assert_eq!(version_description(&exact), exact);
+    }
+
+    #[tokio::test]
+    async fn skips_projects_without_the_version() {
+        let (url, received) = test_server::serve(vec![
+            (
+                "GET /rest/api/2/project/ABC/versions ",
+                200,
+                json!([{ "id": "10", "name": "1.0" }, { "id": "11", "name": "2.0" }]),
+            ),
+            (
+                "GET /rest/api/2/project/XYZ/versions ",
+                200,
+                json!([{ "id": "20", "name": "1.0" }]),
+            ),
+            ("PUT /rest/api/2/version/11 ", 200, json!({})),
+        ])
+        .await;
+        let config = config(url);
+        let writes = plan_writes(&keys(&["ABC-1", "XYZ-3"]), &release());
+
+        let plan = apply_writes(&config, writes.clone(), true).await;
+        assert!(plan
+            .iter()
+            .all(|o| o.dry_run && !o.applied && o.error.is_none()));
+        assert_eq!(plan[0].skipped, None);
+        assert_eq!(
+            plan[1].skipped.as_deref(),
+            Some("XYZ has no version named 2.0")
+        );
+        assert!(!received
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|r| r.line.starts_with("PUT ")));
+
+        let outcomes = apply_writes(&config, writes, false).await;
+        assert!(outcomes[0].applied);
+        assert!(!outcomes[1].applied);
+        assert!(outcomes[1].skipped.is_some());
+        let received = received.lock().unwrap();
+        let puts: Vec<&str> = received
+            .iter()
+            .filter(|r| r.line.starts_with("PUT "))
+            .map(|r| r.body.as_str())
+            .collect();
+        assert_eq!(puts, vec![r#"{"description":"Faster exports."}"#]);
+    }
+}
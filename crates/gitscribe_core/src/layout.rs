@@ -0,0 +1,126 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::jira::JiraIssue;
+use crate::prompt;
+use crate::style::StyleExample;
+
+/// The context layout used when no template is given. Renders the same
+/// Markdown `generate_context` has always produced.
+pub const DEFAULT_CONTEXT_TEMPLATE: &str = r###"# Release Context
+
+## Strategic Context / Adhoc Notes
+{{notes}}
+{{jira_section}}{{previous_notes_section}}{{style_examples_section}}
+## Commit History
+{{commit_log}}
+
+## Code Changes
+```diff
+{{diff}}
+```"###;
+
+/// A linked Jira issue as seen by context templates.
+#[derive(Clone, Debug, Serialize)]
+pub struct IssueData {
+    pub key: String,
+    pub summary: String,
+    pub issue_type: String,
+    pub status: String,
+    pub updated: String,
+    pub resolution_date: String,
+    pub description: String,
+    pub comments: Vec<CommentData>,
+    /// The issue rendered the way the built-in layout shows it.
+    pub section: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CommentData {
+    pub date: String,
+    pub body: String,
+}
+
+impl IssueData {
+    pub fn new(issue: &JiraIssue) -> Self {
+        let comments: Vec<CommentData> = issue
+            .comments
+            .iter()
+            .map(|(body, date)| CommentData {
+                date: date.as_deref().unwrap_or("Unknown Date").to_string(),
+                body: body.clone(),
+            })
+            .collect();
+
+        let comments_text = if comments.is_empty() {
+            "No comments.".to_string()
+        } else {
+            comments
+                .iter()
+                .map(|c| format!("- [{}] {}", c.date, c.body.replace('\n', "\n  ")))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let resolution_date = issue.resolution_date.as_deref().unwrap_or("N/A");
+        let description = issue
+            .description
+            .as_deref()
+            .unwrap_or("No description provided.");
+
+        let section = format!(
+            "### {} {}\n**Type:** {} | **Status:** {}\n**Updated:** {} | **Resolved:** {}\n\n**Description:**\n{}\n\n**Comments:**\n{}\n\n---\n",
+            issue.key,
+            issue.summary,
+            issue.issue_type,
+            issue.status,
+            issue.updated,
+            resolution_date,
+            description,
+            comments_text
+        );
+
+        IssueData {
+            key: issue.key.clone(),
+            summary: issue.summary.clone(),
+            issue_type: issue.issue_type.clone(),
+            status: issue.status.clone(),
+            updated: issue.updated.clone(),
+            resolution_date: resolution_date.to_string(),
+            description: description.to_string(),
+            comments,
+            section,
+        }
+    }
+}
+
+/// Everything a context template can refer to.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ContextData {
+    pub start: String,
+    pub end: String,
+    pub notes: String,
+    /// `git log` output, one `- [date] subject` line per commit.
+    pub commit_log: String,
+    pub commit_count: usize,
+    pub diff: String,
+    pub issues: Vec<IssueData>,
+    /// The "Linked Jira Issues" section of the built-in layout, or empty.
+    pub jira_section: String,
+    /// Notes stored in the repository for the start ref, or empty.
+    pub previous_notes: String,
+    /// The "Previous Release Notes" section of the built-in layout, or empty.
+    pub previous_notes_section: String,
+    /// Notes of earlier releases whose voice the model should match, each
+    /// with a `label` and `text`.
+    pub style_examples: Vec<StyleExample>,
+    /// The "Style Examples" section of the built-in layout, or empty.
+    pub style_examples_section: String,
+}
+
+/// Renders the release context with a Handlebars `template`, or the built-in
+/// layout when `None`.
+pub fn render_context(template: Option<&str>, data: &ContextData) -> Result<String> {
+    prompt::engine()
+        .render_template(template.unwrap_or(DEFAULT_CONTEXT_TEMPLATE), data)
+        .context("Failed to render context template")
+}
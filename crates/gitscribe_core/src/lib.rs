@@ -1,12 +1,36 @@
 use anyhow::{bail, Context, Result};
 use futures::StreamExt;
-use serde_json::json;
 use std::fs;
+use std::io::Write;
 use std::path::Path;
-use std::process::Command;
-use std::time::Duration;
+use std::process::{Command, Output, Stdio};
 
+pub use tokio_util::sync::CancellationToken;
+
+pub mod cache;
+pub mod chat;
+pub mod confluence;
+pub mod credentials;
+pub mod git_notes;
+pub mod grounding;
 pub mod jira;
+pub mod layout;
+pub mod notify;
+pub mod ollama;
+pub mod prompt;
+pub mod publish;
+pub mod redact;
+pub mod render;
+pub mod report;
+pub mod structured;
+pub mod style;
+pub mod templates;
+#[cfg(test)]
+mod test_server;
+pub mod translate;
+pub mod validate;
+
+pub use ollama::{call_ollama, list_ollama_models, OllamaOptions, OllamaResponse, OllamaStats};
 
 pub fn run_git_command(args: &[&str], cwd: Option<&Path>) -> Result<String> {
     let mut cmd = Command::new("git");
@@ -18,7 +42,40 @@ pub fn run_git_command(args: &[&str], cwd: Option<&Path>) -> Result<String> {
     let output = cmd
         .output()
         .context(format!("Failed to execute git command: {:?}", args))?;
+    git_output(args, output)
+}
+
+/// Like [`run_git_command`], writing `input` to git's standard input, e.g.
+/// for `git tag -F -`.
+pub fn run_git_command_with_input(
+    args: &[&str],
+    input: &str,
+    cwd: Option<&Path>,
+) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(path) = cwd {
+        cmd.current_dir(path);
+    }
 
+    let mut child = cmd
+        .spawn()
+        .context(format!("Failed to execute git command: {:?}", args))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .context(format!("Failed to write to git command: {:?}", args))?;
+    }
+    let output = child
+        .wait_with_output()
+        .context(format!("Failed to execute git command: {:?}", args))?;
+    git_output(args, output)
+}
+
+fn git_output(args: &[&str], output: Output) -> Result<String> {
     if output.status.success() {
         Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
     } else {
@@ -47,14 +104,78 @@ pub fn read_file_content(file_path: Option<&String>, description: &str) -> Resul
     }
 }
 
-pub fn get_git_log(start: &str, end: &str, cwd: Option<&Path>) -> Result<String> {
+/// Restricts the log and diff to a subset of the repository.
+///
+/// Paths are git pathspecs relative to the repository root. An empty filter
+/// covers the whole tree.
+#[derive(Clone, Debug, Default)]
+pub struct PathFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl PathFilter {
+    /// Builds a filter from `--path` style values, where a leading `!` or
+    /// `:!` marks an exclusion (e.g. `src/`, `!src/tests`).
+    pub fn from_specs<S: AsRef<str>>(specs: &[S]) -> Self {
+        let mut filter = PathFilter::default();
+        for spec in specs {
+            let spec = spec.as_ref().trim();
+            if spec.is_empty() {
+                continue;
+            }
+            if let Some(path) = spec.strip_prefix(":!").or_else(|| spec.strip_prefix('!')) {
+                filter.exclude.push(path.to_string());
+            } else {
+                filter.include.push(spec.to_string());
+            }
+        }
+        filter
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    fn pathspecs(&self) -> Vec<String> {
+        let mut specs: Vec<String> = if self.include.is_empty() {
+            vec![".".to_string()]
+        } else {
+            self.include.clone()
+        };
+        specs.extend(self.exclude.iter().map(|p| format!(":(exclude){}", p)));
+        specs
+    }
+}
+
+pub fn get_git_log(
+    start: &str,
+    end: &str,
+    paths: &PathFilter,
+    cwd: Option<&Path>,
+) -> Result<String> {
     let range = format!("{}..{}", start, end);
-    run_git_command(&["log", "--pretty=format:- [%ai] %s", &range], cwd)
+    let mut cmd_args = vec!["log", "--pretty=format:- [%ai] %s", &range];
+
+    let pathspecs = paths.pathspecs();
+    if !paths.is_empty() {
+        cmd_args.push("--");
+        cmd_args.extend(pathspecs.iter().map(|s| s.as_str()));
+    }
+
+    run_git_command(&cmd_args, cwd)
 }
 
-pub fn get_git_diff(start: &str, end: &str, cwd: Option<&Path>) -> Result<String> {
+pub fn get_git_diff(
+    start: &str,
+    end: &str,
+    paths: &PathFilter,
+    cwd: Option<&Path>,
+) -> Result<String> {
     let range = format!("{}..{}", start, end);
-    let mut cmd_args = vec!["diff", &range, "--", "."];
+    let pathspecs = paths.pathspecs();
+    let mut cmd_args = vec!["diff", &range, "--"];
+    cmd_args.extend(pathspecs.iter().map(|s| s.as_str()));
 
     let excludes = [
         ":(exclude)package-lock.json",
@@ -95,198 +216,146 @@ pub async fn generate_context(
     start: &str,
     end: &str,
     notes: Option<String>,
+    paths: &PathFilter,
     cwd: Option<&Path>,
     jira_config: Option<jira::JiraConfig>,
+    layout: Option<&str>,
 ) -> Result<String> {
+    generate_context_with_stats(
+        start,
+        end,
+        notes,
+        paths,
+        cwd,
+        jira_config,
+        layout,
+        None,
+        None,
+        Some(&redact::Redactor::builtin()),
+    )
+    .await
+    .map(|(context, _)| context)
+}
+
+/// Same as [`generate_context`], also returning what went into the context.
+///
+/// `layout` is a Handlebars template for the context (see [`layout::ContextData`]
+/// for the available fields); `None` uses [`layout::DEFAULT_CONTEXT_TEMPLATE`].
+/// `previous_notes` are the published notes of the start ref, which the model
+/// is told not to repeat. `style` selects earlier release notes whose voice
+/// the model should match (see [`style::load_examples`]). With a `redactor`, secrets and personal data are
+/// masked in the finished context and listed in the stats.
+#[allow(clippy::too_many_arguments)]
+pub async fn generate_context_with_stats(
+    start: &str,
+    end: &str,
+    notes: Option<String>,
+    paths: &PathFilter,
+    cwd: Option<&Path>,
+    jira_config: Option<jira::JiraConfig>,
+    layout: Option<&str>,
+    previous_notes: Option<&str>,
+    style: Option<&style::StyleSettings>,
+    redactor: Option<&redact::Redactor>,
+) -> Result<(String, report::ContextStats)> {
     let notes_content = notes.unwrap_or_else(|| "No adhoc notes provided.".to_string());
-    let log_content = get_git_log(start, end, cwd)?;
-    let diff_content = get_git_diff(start, end, cwd)?;
+    let log_content = get_git_log(start, end, paths, cwd)?;
+    let diff_content = get_git_diff(start, end, paths, cwd)?;
+
+    let mut stats = report::ContextStats {
+        commit_count: log_content.lines().filter(|l| !l.trim().is_empty()).count(),
+        diff_files: diff_content
+            .lines()
+            .filter(|l| l.starts_with("diff --git "))
+            .count(),
+        diff_bytes: diff_content.len(),
+        ..Default::default()
+    };
 
-    let mut jira_section = String::new();
+    let mut issues = Vec::new();
 
     if let Some(config) = jira_config {
         let keys = jira::extract_issue_keys(&log_content);
         if !keys.is_empty() {
             let client = reqwest::Client::new();
+            let cache = cache::JiraCache::for_server(&config.url, config.cache_ttl).ok();
 
             let fetches = futures::stream::iter(keys)
                 .map(|key| {
                     let client = &client;
                     let config = &config;
-                    async move { jira::fetch_issue(client, config, &key).await }
+                    let cache = cache.as_ref();
+                    async move { jira::fetch_issue_cached(client, config, cache, &key).await }
                 })
                 .buffer_unordered(5) // Concurrency limit
                 .collect::<Vec<_>>()
                 .await;
 
-            let mut issue_sections = String::new();
-            for res in fetches {
-                if let Ok(Some(issue)) = res {
-                    let comments_text = if issue.comments.is_empty() {
-                        "No comments.".to_string()
-                    } else {
-                        issue
-                            .comments
-                            .iter()
-                            .map(|(body, date)| {
-                                let date_str = date.as_deref().unwrap_or("Unknown Date");
-                                format!("- [{}] {}", date_str, body.replace('\n', "\n  "))
-                            })
-                            .collect::<Vec<_>>()
-                            .join("\n")
-                    };
-
-                    issue_sections.push_str(&format!(
-                        "### {} {}\n**Type:** {} | **Status:** {}\n**Updated:** {} | **Resolved:** {}\n\n**Description:**\n{}\n\n**Comments:**\n{}\n\n---\n",
-                        issue.key,
-                        issue.summary,
-                        issue.issue_type,
-                        issue.status,
-                        issue.updated,
-                        issue.resolution_date.as_deref().unwrap_or("N/A"),
-                        issue.description.as_deref().unwrap_or("No description provided."),
-                        comments_text
-                    ));
-                }
-            }
-
-            if !issue_sections.is_empty() {
-                jira_section = format!("\n## Linked Jira Issues\n\n{}", issue_sections);
+            let mut fetch_stats = jira::FetchStats::default();
+            for (_, source) in &fetches {
+                fetch_stats.record(*source);
             }
+            eprintln!("Jira issues: {}", fetch_stats);
+            stats.jira = Some(fetch_stats);
+
+            // Fetches finish in any order; sort so the context is stable between runs
+            issues = fetches
+                .iter()
+                .filter_map(|(issue, _)| issue.as_ref().map(layout::IssueData::new))
+                .collect();
+            issues.sort_by(|a, b| a.key.cmp(&b.key));
+            stats.issue_keys = issues.iter().map(|i| i.key.clone()).collect();
         }
     }
 
-    Ok(format!(
-        r###"# Release Context
-
-## Strategic Context / Adhoc Notes
-{}
-{}
-## Commit History
-{}
-
-## Code Changes
-```diff
-{}
-```"###,
-        notes_content, jira_section, log_content, diff_content
-    ))
-}
-
-pub async fn call_ollama<F>(
-    model: &str,
-    url: &str,
-    prompt: &str,
-    system: Option<&String>,
-    callback: Option<F>,
-) -> Result<String>
-where
-    F: Fn(&str) + Send + Sync + 'static,
-{
-    // Increase timeout to 5 minutes (300s) for large models
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(300))
-        .build()
-        .context("Failed to build HTTP client")?;
-
-    let stream = callback.is_some();
-
-    let mut payload = json!({
-        "model": model,
-        "stream": stream,
-        "prompt": prompt
-    });
-
-    if let Some(sys_msg) = system {
-        payload["system"] = json!(sys_msg);
-    }
-
-    println!(
-        "Connecting to Ollama ({}) with model '{}' (Streaming: {})...",
-        url, model, stream
-    );
-
-    let res = client
-        .post(url)
-        .json(&payload)
-        .send()
-        .await
-        .context("Failed to send request to Ollama")?;
-
-    if !res.status().is_success() {
-        let status = res.status();
-        let text = res.text().await.unwrap_or_default();
-        bail!("Ollama API error ({}): {}", status, text);
-    }
-
-    if let Some(cb) = callback {
-        // Streaming Mode
-        let mut full_response = String::new();
-        let mut stream = res.bytes_stream();
-
-        while let Some(item) = stream.next().await {
-            let chunk = item.context("Error reading stream chunk")?;
-            let chunk_str = String::from_utf8_lossy(&chunk);
-
-            // Parse JSON chunk (Ollama sends multiple JSON objects in stream)
-            // Often partial chunks arrive, but reqwest bytes_stream usually gives full chunks or we need to buffer line by line.
-            // Ollama stream format is one JSON object per line.
-            for line in chunk_str.split('\n') {
-                if line.trim().is_empty() {
-                    continue;
-                }
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
-                    if let Some(token) = json["response"].as_str() {
-                        full_response.push_str(token);
-                        cb(token);
-                    }
-                    if json["done"].as_bool().unwrap_or(false) {
-                        break;
-                    }
-                }
-            }
-        }
-        Ok(full_response)
+    let jira_section = if issues.is_empty() {
+        String::new()
     } else {
-        // Non-Streaming Mode
-        let response_json: serde_json::Value = res
-            .json()
-            .await
-            .context("Failed to parse Ollama JSON response")?;
-        response_json["response"]
-            .as_str()
-            .map(|s| s.to_string())
-            .context("Ollama response missing 'response' field")
-    }
-}
-
-pub async fn list_ollama_models(base_url: &str) -> Result<Vec<String>> {
-    let client = reqwest::Client::new();
-    let url = format!("{}/api/tags", base_url.trim_end_matches('/'));
-
-    let res = client
-        .get(&url)
-        .send()
-        .await
-        .context("Failed to connect to Ollama. Is it running?")?;
+        let sections: String = issues.iter().map(|i| i.section.as_str()).collect();
+        format!("\n## Linked Jira Issues\n\n{}", sections)
+    };
 
-    if !res.status().is_success() {
-        let status = res.status();
-        let text = res.text().await.unwrap_or_default();
-        bail!("Ollama API error ({}): {}", status, text);
+    let previous_notes = previous_notes.unwrap_or_default().trim().to_string();
+    let previous_notes_section = if previous_notes.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "\n## Previous Release Notes ({}, already published; do not repeat)\n\n{}\n",
+            start, previous_notes
+        )
+    };
+
+    let style_examples = match style {
+        Some(settings) => style::load_examples(settings, start, cwd)?,
+        None => Vec::new(),
+    };
+    stats.style_examples = style_examples.iter().map(|e| e.label.clone()).collect();
+    let style_examples_section = style::examples_section(&style_examples);
+
+    let data = layout::ContextData {
+        start: start.to_string(),
+        end: end.to_string(),
+        notes: notes_content,
+        commit_count: stats.commit_count,
+        commit_log: log_content,
+        diff: diff_content,
+        issues,
+        jira_section,
+        previous_notes,
+        previous_notes_section,
+        style_examples,
+        style_examples_section,
+    };
+    let mut context = layout::render_context(layout, &data)?;
+    stats.sources = grounding::context_sources(&data);
+    if let Some(redactor) = redactor {
+        (context, stats.redactions) = redactor.redact(&context);
+        for source in &mut stats.sources {
+            source.text = redactor.redact(&source.text).0;
+            source.detail = redactor.redact(&source.detail).0;
+        }
     }
+    stats.context_bytes = context.len();
 
-    let response_json: serde_json::Value = res
-        .json()
-        .await
-        .context("Failed to parse Ollama JSON response")?;
-
-    let models = response_json["models"]
-        .as_array()
-        .context("Invalid response: 'models' field missing or not an array")?
-        .iter()
-        .filter_map(|m| m["name"].as_str().map(|s| s.to_string()))
-        .collect();
-
-    Ok(models)
+    Ok((context, stats))
 }
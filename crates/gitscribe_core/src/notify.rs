@@ -0,0 +1,297 @@
+use anyhow::{bail, Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::prompt;
+use crate::render;
+
+const SLACK_TEMPLATE: &str = r#"{
+  "text": "{{title}}",
+  "blocks": [
+    { "type": "header", "text": { "type": "plain_text", "text": "{{title}}" } },
+    { "type": "section", "text": { "type": "mrkdwn", "text": "{{summary}}" } }{{#if url}},
+    { "type": "section", "text": { "type": "mrkdwn", "text": "<{{url}}|Read the full release notes>" } }{{/if}}
+  ]
+}
+"#;
+
+const TEAMS_TEMPLATE: &str = r#"{
+  "type": "message",
+  "attachments": [{
+    "contentType": "application/vnd.microsoft.card.adaptive",
+    "content": {
+      "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+      "type": "AdaptiveCard",
+      "version": "1.4",
+      "body": [
+        { "type": "TextBlock", "text": "{{title}}", "size": "Large", "weight": "Bolder", "wrap": true },
+        { "type": "TextBlock", "text": "{{summary_markdown}}", "wrap": true }
+      ]{{#if url}},
+      "actions": [{ "type": "Action.OpenUrl", "title": "Read the full release notes", "url": "{{url}}" }]{{/if}}
+    }
+  }]
+}
+"#;
+
+const GENERIC_TEMPLATE: &str = r#"{
+  "title": "{{title}}",
+  "summary": "{{summary}}",
+  "summary_markdown": "{{summary_markdown}}",
+  "version": {{#if version}}"{{version}}"{{else}}null{{/if}},
+  "start": "{{start}}",
+  "end": "{{end}}",
+  "url": {{#if url}}"{{url}}"{{else}}null{{/if}}
+}
+"#;
+
+/// Payload shape expected by the receiving endpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    Slack,
+    Teams,
+    Generic,
+}
+
+impl WebhookFormat {
+    /// Guesses the format from well-known webhook hosts, else `Generic`.
+    pub fn detect(url: &str) -> Self {
+        let host = host(url);
+        if host == "hooks.slack.com" {
+            Self::Slack
+        } else if host.ends_with(".webhook.office.com") || host.ends_with(".logic.azure.com") {
+            Self::Teams
+        } else {
+            Self::Generic
+        }
+    }
+
+    /// Built-in Handlebars template for the JSON payload.
+    pub fn template(self) -> &'static str {
+        match self {
+            Self::Slack => SLACK_TEMPLATE,
+            Self::Teams => TEAMS_TEMPLATE,
+            Self::Generic => GENERIC_TEMPLATE,
+        }
+    }
+}
+
+impl FromStr for WebhookFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "slack" => Ok(Self::Slack),
+            "teams" | "msteams" => Ok(Self::Teams),
+            "generic" | "json" => Ok(Self::Generic),
+            other => bail!(
+                "Unknown webhook format '{}' (expected slack, teams or generic)",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for WebhookFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Slack => "Slack",
+            Self::Teams => "Teams",
+            Self::Generic => "webhook",
+        })
+    }
+}
+
+/// Host part of a URL. Webhook paths often embed secrets, so only the host
+/// is ever printed.
+pub fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?']).next().unwrap_or(rest)
+}
+
+/// Fields available to payload templates. Values are JSON-escaped, so
+/// templates put them inside string literals.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Announcement {
+    pub title: String,
+    /// Executive summary as plain text.
+    pub summary: String,
+    /// Executive summary as written in the notes.
+    pub summary_markdown: String,
+    pub version: Option<String>,
+    pub start: String,
+    pub end: String,
+    /// Link to the full notes, e.g. the published release.
+    pub url: Option<String>,
+}
+
+/// The "Executive Summary" section of the notes (any heading containing
+/// "summary"), or their first paragraph if there is none.
+pub fn executive_summary(notes: &str) -> String {
+    let lines: Vec<&str> = notes.lines().collect();
+    let heading_level = |line: &str| {
+        let level = line.chars().take_while(|c| *c == '#').count();
+        (level > 0 && line[level..].starts_with(' ')).then_some(level)
+    };
+    let section = lines.iter().enumerate().find_map(|(index, line)| {
+        let level = heading_level(line)?;
+        line[level..]
+            .to_lowercase()
+            .contains("summary")
+            .then_some((index, level))
+    });
+    let body: Vec<&str> = match section {
+        Some((index, level)) => lines[index + 1..]
+            .iter()
+            .take_while(|line| heading_level(line).is_none_or(|l| l > level))
+            .copied()
+            .collect(),
+        None => lines
+            .iter()
+            .skip_while(|line| line.trim().is_empty() || heading_level(line).is_some())
+            .take_while(|line| !line.trim().is_empty() && heading_level(line).is_none())
+            .copied()
+            .collect(),
+    };
+    body.join("\n").trim().to_string()
+}
+
+impl Announcement {
+    /// Builds the announcement from final Markdown notes.
+    pub fn from_notes(notes: &str, start: &str, end: &str) -> Self {
+        let summary_markdown = executive_summary(notes);
+        Self {
+            title: crate::publish::release_title(notes, end),
+            summary: render::to_plain_text(&summary_markdown).trim().to_string(),
+            summary_markdown,
+            start: start.to_string(),
+            end: end.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Renders the JSON payload with a Handlebars `template`, or the built-in
+/// one for `format`.
+pub fn render_payload(
+    format: WebhookFormat,
+    template: Option<&str>,
+    announcement: &Announcement,
+) -> Result<String> {
+    let mut handlebars = prompt::engine();
+    handlebars.register_escape_fn(|value| {
+        let quoted = serde_json::to_string(value).unwrap_or_default();
+        quoted[1..quoted.len() - 1].to_string()
+    });
+    let payload = handlebars
+        .render_template(template.unwrap_or(format.template()), announcement)
+        .context("Failed to render webhook template")?;
+    serde_json::from_str::<Value>(&payload)
+        .context("The webhook template did not produce valid JSON")?;
+    Ok(payload)
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NotifyOutcome {
+    pub format: WebhookFormat,
+    pub dry_run: bool,
+    /// Host the payload was sent to.
+    pub host: String,
+}
+
+impl fmt::Display for NotifyOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = if self.dry_run { "Would post" } else { "Posted" };
+        write!(f, "{} a {} summary to {}", verb, self.format, self.host)
+    }
+}
+
+/// Posts `payload` to the webhook. A dry run sends nothing.
+pub async fn send(
+    url: &str,
+    format: WebhookFormat,
+    payload: &str,
+    dry_run: bool,
+) -> Result<NotifyOutcome> {
+    let outcome = NotifyOutcome {
+        format,
+        dry_run,
+        host: host(url).to_string(),
+    };
+    if dry_run {
+        return Ok(outcome);
+    }
+    let response = Client::new()
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(payload.to_string())
+        .send()
+        .await
+        // The error would otherwise print the full URL, secret path included
+        .map_err(|e| e.without_url())
+        .context(format!("Failed to reach the webhook at {}", outcome.host))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!(
+            "The webhook at {} returned {}: {}",
+            outcome.host,
+            status,
+            body.trim()
+        );
+    }
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn posts_payload_to_webhook() {
+        let (base_url, received) =
+            test_server::serve(vec![("POST /services/", 200, json!({ "ok": true }))]).await;
+        let url = format!("{}/services/T000/B000/secret", base_url);
+        let notes =
+            "# Acme 2.0\n\n## Executive Summary\nFaster \"exports\".\n\n## Fixes\n- A fix\n";
+        let mut announcement = Announcement::from_notes(notes, "v1.0", "v2.0");
+        announcement.url = Some("https://example.test/releases/v2.0".to_string());
+        let payload = render_payload(WebhookFormat::Slack, None, &announcement).unwrap();
+
+        let outcome = send(&url, WebhookFormat::Slack, &payload, false)
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.host, url.split('/').nth(2).unwrap());
+        let received = received.lock().unwrap();
+        assert!(received[0]
+            .line
+            .starts_with("POST /services/T000/B000/secret "));
+        let body: Value = serde_json::from_str(&received[0].body).unwrap();
+        assert_eq!(body["text"], "Acme 2.0");
+        assert_eq!(body["blocks"][1]["text"]["text"], "Faster \"exports\".");
+        assert!(body["blocks"][2]["text"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("https://example.test/releases/v2.0"));
+    }
+
+    #[tokio::test]
+    async fn errors_do_not_reveal_the_webhook_path() {
+        let base_url = test_server::closed_url().await;
+        let url = format!("{}/services/T000/B000/secret", base_url);
+
+        let error = send(&url, WebhookFormat::Generic, "{}", false)
+            .await
+            .unwrap_err();
+
+        let message = format!("{:#}", error);
+        assert!(message.contains(base_url.trim_start_matches("http://")));
+        assert!(!message.contains("secret"), "{}", message);
+    }
+}
@@ -0,0 +1,413 @@
+use anyhow::{bail, Context, Result};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Timing and token counts Ollama reports in its final (`done`) message.
+/// Durations are in nanoseconds.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct OllamaStats {
+    pub total_duration: Option<u64>,
+    pub load_duration: Option<u64>,
+    pub prompt_eval_count: Option<u64>,
+    pub prompt_eval_duration: Option<u64>,
+    pub eval_count: Option<u64>,
+    pub eval_duration: Option<u64>,
+    pub done_reason: Option<String>,
+}
+
+/// Sampling parameters sent as Ollama's `options`, plus `keep_alive`.
+/// Unset fields fall back to the model's defaults.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    /// How long the model stays loaded after the request, e.g. "5m" or "0".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
+    /// Constrains the response to `"json"` or a JSON schema.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<serde_json::Value>,
+}
+
+impl OllamaOptions {
+    /// Everything that can change the output, for use in cache keys.
+    /// `keep_alive` only affects model residency, so it is left out.
+    pub fn sampling(&self) -> serde_json::Value {
+        let mut options = serde_json::to_value(self).unwrap_or_else(|_| json!({}));
+        if let Some(map) = options.as_object_mut() {
+            map.remove("keep_alive");
+        }
+        options
+    }
+
+    /// Adds the options to a request payload. `keep_alive` and `format` are
+    /// top-level fields in the Ollama API; everything else goes under `options`.
+    pub fn apply(&self, payload: &mut serde_json::Value) {
+        let mut options = self.sampling();
+        if let Some(map) = options.as_object_mut() {
+            map.remove("format");
+            if !map.is_empty() {
+                payload["options"] = options;
+            }
+        }
+        if let Some(keep_alive) = &self.keep_alive {
+            payload["keep_alive"] = json!(keep_alive);
+        }
+        if let Some(format) = &self.format {
+            payload["format"] = format.clone();
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct OllamaResponse {
+    pub text: String,
+    /// Missing when the run was cancelled before Ollama sent its final message.
+    pub stats: Option<OllamaStats>,
+    /// Whether the run was cancelled; `text` then holds what arrived before.
+    pub cancelled: bool,
+}
+
+impl OllamaResponse {
+    /// A response cut short by cancellation, keeping the text received so far.
+    pub(crate) fn interrupted(text: String) -> Self {
+        Self {
+            text,
+            stats: None,
+            cancelled: true,
+        }
+    }
+}
+
+/// Splits a byte stream into newline-delimited records, holding back any
+/// trailing partial line until the rest of it arrives.
+#[derive(Default)]
+pub struct LineDecoder {
+    buffer: Vec<u8>,
+}
+
+impl LineDecoder {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+
+    /// Returns whatever is left once the stream has ended.
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = String::from_utf8_lossy(&self.buffer).trim().to_string();
+        self.buffer.clear();
+        (!rest.is_empty()).then_some(rest)
+    }
+}
+
+/// Outcome of feeding one stream record to [`handle_stream_line`].
+enum StreamEvent {
+    Token,
+    Done(OllamaStats),
+}
+
+fn handle_stream_line<F>(line: &str, full_response: &mut String, cb: &F) -> Result<StreamEvent>
+where
+    F: Fn(&str),
+{
+    let json: serde_json::Value =
+        serde_json::from_str(line).context(format!("Invalid JSON in Ollama stream: {}", line))?;
+
+    if let Some(err) = json["error"].as_str() {
+        bail!("Ollama stream error: {}", err);
+    }
+    // `/api/generate` streams `response`, `/api/chat` streams `message.content`
+    let token = json["response"]
+        .as_str()
+        .or_else(|| json["message"]["content"].as_str());
+    if let Some(token) = token {
+        full_response.push_str(token);
+        cb(token);
+    }
+    if json["done"].as_bool().unwrap_or(false) {
+        let stats = serde_json::from_value(json).unwrap_or_default();
+        return Ok(StreamEvent::Done(stats));
+    }
+    Ok(StreamEvent::Token)
+}
+
+/// POSTs a request to Ollama, returning `None` if `cancel` fires first.
+pub(crate) async fn post_json(
+    url: &str,
+    payload: &serde_json::Value,
+    cancel: &CancellationToken,
+) -> Result<Option<reqwest::Response>> {
+    // Increase timeout to 5 minutes (300s) for large models
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(300))
+        .build()
+        .context("Failed to build HTTP client")?;
+
+    let res = tokio::select! {
+        res = client.post(url).json(payload).send() => {
+            res.context("Failed to send request to Ollama")?
+        }
+        _ = cancel.cancelled() => return Ok(None),
+    };
+
+    if !res.status().is_success() {
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        bail!("Ollama API error ({}): {}", status, text);
+    }
+    Ok(Some(res))
+}
+
+/// Reads a streamed Ollama response (`/api/generate` or `/api/chat`) until the
+/// final `done` message, passing each token to `cb`.
+pub(crate) async fn read_stream<F>(
+    res: reqwest::Response,
+    cb: &F,
+    cancel: &CancellationToken,
+) -> Result<OllamaResponse>
+where
+    F: Fn(&str),
+{
+    // One JSON object per line, but lines may straddle network chunks
+    let mut full_response = String::new();
+    let mut decoder = LineDecoder::default();
+    let mut stream = res.bytes_stream();
+
+    loop {
+        let item = tokio::select! {
+            item = stream.next() => item,
+            _ = cancel.cancelled() => break,
+        };
+        let Some(item) = item else {
+            break;
+        };
+        let chunk = item.context("Error reading stream chunk")?;
+
+        for line in decoder.push(&chunk) {
+            if let StreamEvent::Done(stats) = handle_stream_line(&line, &mut full_response, cb)? {
+                return Ok(OllamaResponse {
+                    text: full_response,
+                    stats: Some(stats),
+                    cancelled: false,
+                });
+            }
+        }
+    }
+
+    // The final message may arrive without a trailing newline
+    if let Some(line) = decoder.finish() {
+        if let StreamEvent::Done(stats) = handle_stream_line(&line, &mut full_response, cb)? {
+            return Ok(OllamaResponse {
+                text: full_response,
+                stats: Some(stats),
+                cancelled: false,
+            });
+        }
+    }
+
+    if !cancel.is_cancelled() {
+        bail!("Ollama stream ended before the response was complete");
+    }
+    Ok(OllamaResponse::interrupted(full_response))
+}
+
+/// Sends the prompt to Ollama, streaming tokens to `callback` when one is given.
+///
+/// Triggering `cancel` drops the in-flight request and returns the text received
+/// so far with `cancelled` set.
+pub async fn call_ollama<F>(
+    model: &str,
+    url: &str,
+    prompt: &str,
+    system: Option<&String>,
+    options: &OllamaOptions,
+    callback: Option<F>,
+    cancel: Option<&CancellationToken>,
+) -> Result<OllamaResponse>
+where
+    F: Fn(&str) + Send + Sync + 'static,
+{
+    let never_cancelled = CancellationToken::new();
+    let cancel = cancel.unwrap_or(&never_cancelled);
+
+    let stream = callback.is_some();
+
+    let mut payload = json!({
+        "model": model,
+        "stream": stream,
+        "prompt": prompt
+    });
+
+    if let Some(sys_msg) = system {
+        payload["system"] = json!(sys_msg);
+    }
+
+    options.apply(&mut payload);
+
+    println!(
+        "Connecting to Ollama ({}) with model '{}' (Streaming: {})...",
+        url, model, stream
+    );
+
+    let Some(res) = post_json(url, &payload, cancel).await? else {
+        return Ok(OllamaResponse::interrupted(String::new()));
+    };
+
+    if let Some(cb) = callback {
+        read_stream(res, &cb, cancel).await
+    } else {
+        // Non-Streaming Mode
+        let response_json: serde_json::Value = tokio::select! {
+            json = res.json() => json.context("Failed to parse Ollama JSON response")?,
+            _ = cancel.cancelled() => return Ok(OllamaResponse::interrupted(String::new())),
+        };
+        if let Some(err) = response_json["error"].as_str() {
+            bail!("Ollama error: {}", err);
+        }
+        let text = response_json["response"]
+            .as_str()
+            .map(|s| s.to_string())
+            .context("Ollama response missing 'response' field")?;
+        Ok(OllamaResponse {
+            text,
+            stats: serde_json::from_value(response_json).ok(),
+            cancelled: false,
+        })
+    }
+}
+
+pub async fn list_ollama_models(base_url: &str) -> Result<Vec<String>> {
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/tags", base_url.trim_end_matches('/'));
+
+    let res = client
+        .get(&url)
+        .send()
+        .await
+        .context("Failed to connect to Ollama. Is it running?")?;
+
+    if !res.status().is_success() {
+        let status = res.status();
+        let text = res.text().await.unwrap_or_default();
+        bail!("Ollama API error ({}): {}", status, text);
+    }
+
+    let response_json: serde_json::Value = res
+        .json()
+        .await
+        .context("Failed to parse Ollama JSON response")?;
+
+    let models = response_json["models"]
+        .as_array()
+        .context("Invalid response: 'models' field missing or not an array")?
+        .iter()
+        .filter_map(|m| m["name"].as_str().map(|s| s.to_string()))
+        .collect();
+
+    Ok(models)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_split_across_pushes_is_joined() {
+        let mut decoder = LineDecoder::default();
+        assert!(decoder.push(br#"{"response":"Hel"#).is_empty());
+        assert_eq!(
+            decoder.push(b"lo\"}\n{\"resp"),
+            vec![r#"{"response":"Hello"}"#]
+        );
+        assert_eq!(
+            decoder.push(b"onse\":\"!\"}\n"),
+            vec![r#"{"response":"!"}"#]
+        );
+        assert_eq!(decoder.finish(), None);
+    }
+
+    #[test]
+    fn finish_returns_last_line_without_newline() {
+        let mut decoder = LineDecoder::default();
+        assert_eq!(
+            decoder.push(b"{\"a\":1}\n{\"done\":true}"),
+            vec!["{\"a\":1}"]
+        );
+        assert_eq!(decoder.finish().as_deref(), Some("{\"done\":true}"));
+        assert_eq!(decoder.finish(), None);
+    }
+
+    #[test]
+    fn multibyte_character_split_across_chunks() {
+        let line = "{\"response\":\"café\"}\n".as_bytes();
+        let split = line.iter().position(|&b| b == 0xC3).unwrap() + 1;
+        let mut decoder = LineDecoder::default();
+        assert!(decoder.push(&line[..split]).is_empty());
+        let lines = decoder.push(&line[split..]);
+        assert_eq!(lines, vec!["{\"response\":\"café\"}"]);
+
+        let mut text = String::new();
+        handle_stream_line(&lines[0], &mut text, &|_| {}).unwrap();
+        assert_eq!(text, "café");
+    }
+
+    #[test]
+    fn error_line_becomes_err() {
+        let mut text = String::new();
+        let err = handle_stream_line(r#"{"error":"model not found"}"#, &mut text, &|_| {})
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("model not found"));
+        assert!(text.is_empty());
+    }
+
+    #[test]
+    fn done_line_fills_stats() {
+        let tokens = std::cell::RefCell::new(Vec::new());
+        let cb = |t: &str| tokens.borrow_mut().push(t.to_string());
+        let mut text = String::new();
+
+        let first = handle_stream_line(
+            r#"{"message":{"content":"Hi"},"done":false}"#,
+            &mut text,
+            &cb,
+        )
+        .unwrap();
+        assert!(matches!(first, StreamEvent::Token));
+
+        let last = handle_stream_line(
+            r#"{"response":"","done":true,"done_reason":"stop","total_duration":5000,"eval_count":42,"prompt_eval_count":7}"#,
+            &mut text,
+            &cb,
+        )
+        .unwrap();
+        let StreamEvent::Done(stats) = last else {
+            panic!("expected a done event");
+        };
+        assert_eq!(stats.eval_count, Some(42));
+        assert_eq!(stats.prompt_eval_count, Some(7));
+        assert_eq!(stats.total_duration, Some(5000));
+        assert_eq!(stats.done_reason.as_deref(), Some("stop"));
+        assert_eq!(text, "Hi");
+        assert_eq!(*tokens.borrow(), vec!["Hi", ""]);
+    }
+}
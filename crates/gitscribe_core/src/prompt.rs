@@ -0,0 +1,143 @@
+use anyhow::{Context, Result};
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::run_git_command;
+use crate::templates;
+
+/// Values available to prompt templates as `{{product_name}}`, `{{version}}`, etc.
+///
+/// Unset values render as the bracketed placeholders the built-in template
+/// used before (`[Product Name]`), so the model and the output validator
+/// still see that something is missing.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PromptVars {
+    pub product_name: Option<String>,
+    pub version: Option<String>,
+    pub release_date: Option<String>,
+    pub repo_name: Option<String>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub audience: Option<String>,
+    /// Additional `{{name}}` values, e.g. from `--var team=Payments`.
+    #[serde(default)]
+    pub extra: BTreeMap<String, String>,
+}
+
+impl PromptVars {
+    /// Fills in what can be read from the repository: its name, the range,
+    /// the end commit's date as release date and, if the end commit is
+    /// tagged, the tag as version.
+    pub fn from_repo(start: &str, end: &str, cwd: Option<&Path>) -> Self {
+        let repo_name = run_git_command(&["rev-parse", "--show-toplevel"], cwd)
+            .ok()
+            .and_then(|top| {
+                Path::new(&top)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+            });
+        let release_date = run_git_command(&["log", "-1", "--format=%cs", end], cwd).ok();
+        let version = run_git_command(&["describe", "--tags", "--exact-match", end], cwd).ok();
+
+        PromptVars {
+            repo_name,
+            release_date,
+            version,
+            start: Some(start.to_string()),
+            end: Some(end.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Returns `self` with every value set in `overrides` replaced.
+    pub fn merge(mut self, overrides: PromptVars) -> Self {
+        fn pick(base: &mut Option<String>, value: Option<String>) {
+            if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+                *base = Some(value);
+            }
+        }
+        pick(&mut self.product_name, overrides.product_name);
+        pick(&mut self.version, overrides.version);
+        pick(&mut self.release_date, overrides.release_date);
+        pick(&mut self.repo_name, overrides.repo_name);
+        pick(&mut self.start, overrides.start);
+        pick(&mut self.end, overrides.end);
+        pick(&mut self.audience, overrides.audience);
+        self.extra.extend(overrides.extra);
+        self
+    }
+
+    fn to_data(&self) -> BTreeMap<String, String> {
+        let or = |value: &Option<String>, placeholder: &str| {
+            value.clone().unwrap_or_else(|| placeholder.to_string())
+        };
+        let mut data = self.extra.clone();
+        data.insert(
+            "product_name".into(),
+            or(&self.product_name, "[Product Name]"),
+        );
+        data.insert("version".into(), or(&self.version, "[Version]"));
+        data.insert(
+            "release_date".into(),
+            or(&self.release_date, "[YYYY-MM-DD]"),
+        );
+        data.insert("repo_name".into(), or(&self.repo_name, "[Repository]"));
+        data.insert("start".into(), or(&self.start, "[Start]"));
+        data.insert("end".into(), or(&self.end, "[End]"));
+        data.insert("audience".into(), or(&self.audience, "[Audience]"));
+        data
+    }
+}
+
+/// Parses `KEY=VALUE` as given to `--var`.
+pub fn parse_var(spec: &str) -> Result<(String, String)> {
+    let (key, value) = spec
+        .split_once('=')
+        .context(format!("Expected KEY=VALUE, got '{}'", spec))?;
+    Ok((key.trim().to_string(), value.to_string()))
+}
+
+/// Renders a Handlebars prompt template, without its settings block.
+/// Templates without `{{` come back unchanged; unknown variables are an error
+/// so typos don't go unnoticed. A literal `{{` is written as `\{{`.
+pub fn render_prompt(template: &str, vars: &PromptVars) -> Result<String> {
+    let (_, body) = templates::parse_template(template)?;
+    engine()
+        .render_template(body, &vars.to_data())
+        .context("Failed to render prompt template (write \\{{ for a literal {{)")
+}
+
+/// Handlebars set up for Markdown templates: strict, without HTML escaping.
+pub(crate) fn engine() -> Handlebars<'static> {
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_braces_stay_literal() {
+        let vars = PromptVars {
+            product_name: Some("Acme".to_string()),
+            ..Default::default()
+        };
+        let prompt = render_prompt("{{product_name}} uses \\{{name}} in its templates", &vars);
+        assert_eq!(prompt.unwrap(), "Acme uses {{name}} in its templates");
+    }
+
+    #[test]
+    fn unknown_front_matter_is_kept() {
+        let template = "---\ntitle: Notes\n---\nWrite notes for {{product_name}}.";
+        let prompt = render_prompt(template, &PromptVars::default()).unwrap();
+        assert_eq!(
+            prompt,
+            "---\ntitle: Notes\n---\nWrite notes for [Product Name]."
+        );
+    }
+}
@@ -0,0 +1,497 @@
+use anyhow::{bail, Context, Result};
+use reqwest::{multipart, Client, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::run_git_command;
+
+/// Hosting service that receives the release.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+}
+
+impl ForgeKind {
+    pub fn default_api_url(self) -> &'static str {
+        match self {
+            Self::GitHub => "https://api.github.com",
+            Self::GitLab => "https://gitlab.com/api/v4",
+        }
+    }
+
+    /// Environment variable read when no token is given.
+    pub fn token_env(self) -> &'static str {
+        match self {
+            Self::GitHub => "GITHUB_TOKEN",
+            Self::GitLab => "GITLAB_TOKEN",
+        }
+    }
+}
+
+impl FromStr for ForgeKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "github" => Ok(Self::GitHub),
+            "gitlab" => Ok(Self::GitLab),
+            other => bail!("Unknown forge '{}' (expected github or gitlab)", other),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ForgeConfig {
+    pub kind: ForgeKind,
+    /// API base URL, e.g. `https://api.github.com` or a local mock server.
+    pub api_url: String,
+    /// `owner/name` on GitHub, `group/project` on GitLab.
+    pub repo: String,
+    pub token: Option<String>,
+}
+
+/// Extracts `owner/name` from a remote such as `git@github.com:owner/name.git`
+/// or `https://gitlab.example.com/group/sub/project`.
+pub fn repo_from_remote(remote: &str) -> Option<String> {
+    let remote = remote.trim().trim_end_matches('/');
+    let path = if let Some((_, rest)) = remote.split_once("://") {
+        rest.split_once('/')?.1
+    } else {
+        remote.split_once(':')?.1
+    };
+    let path = path.trim_start_matches('/').trim_end_matches(".git");
+    path.contains('/').then(|| path.to_string())
+}
+
+/// Repository path of the `origin` remote.
+pub fn detect_repo(cwd: Option<&Path>) -> Result<String> {
+    let remote = run_git_command(&["remote", "get-url", "origin"], cwd)
+        .context("Could not read the origin remote; pass the repository explicitly")?;
+    repo_from_remote(&remote).context(format!(
+        "Could not find a repository path in remote '{}'",
+        remote.trim()
+    ))
+}
+
+pub fn tag_exists(tag: &str, cwd: Option<&Path>) -> bool {
+    let tag_ref = format!("refs/tags/{}", tag);
+    run_git_command(&["rev-parse", "--verify", "--quiet", &tag_ref], cwd).is_ok()
+}
+
+/// Release title: the notes' first `# ` heading, or the tag.
+pub fn release_title(notes: &str, tag: &str) -> String {
+    notes
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| tag.to_string())
+}
+
+#[derive(Clone, Debug)]
+pub struct Attachment {
+    pub file_name: String,
+    pub content: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReleaseDraft {
+    pub tag: String,
+    pub name: String,
+    pub body: String,
+    pub attachment: Option<Attachment>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PublishAction {
+    Create,
+    Update,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PublishOutcome {
+    pub action: PublishAction,
+    pub dry_run: bool,
+    pub repo: String,
+    pub tag: String,
+    /// Web page of the release; for dry runs only known when it exists.
+    pub url: Option<String>,
+    pub attachment: Option<String>,
+}
+
+impl fmt::Display for PublishOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = match (self.dry_run, self.action) {
+            (true, PublishAction::Create) => "Would create",
+            (true, PublishAction::Update) => "Would update",
+            (false, PublishAction::Create) => "Created",
+            (false, PublishAction::Update) => "Updated",
+        };
+        write!(f, "{} release {} on {}", verb, self.tag, self.repo)?;
+        if let Some(attachment) = &self.attachment {
+            write!(f, " with {} attached", attachment)?;
+        }
+        if let Some(url) = &self.url {
+            write!(f, ": {}", url)?;
+        }
+        Ok(())
+    }
+}
+
+/// Creates the release for `draft.tag`, or updates its title and body if it
+/// exists. A dry run only looks the release up.
+pub async fn publish_release(
+    config: &ForgeConfig,
+    draft: &ReleaseDraft,
+    dry_run: bool,
+) -> Result<PublishOutcome> {
+    if !dry_run && config.token.is_none() {
+        bail!(
+            "Publishing to {} needs an access token (set {})",
+            config.repo,
+            config.kind.token_env()
+        );
+    }
+    let client = Client::new();
+    match config.kind {
+        ForgeKind::GitHub => publish_github(&client, config, draft, dry_run).await,
+        ForgeKind::GitLab => publish_gitlab(&client, config, draft, dry_run).await,
+    }
+}
+
+/// Percent-encodes one URL path segment, e.g. `group/project` as `group%2Fproject`.
+fn encode_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+async fn check(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let url = response.url().to_string();
+    let body = response.text().await.unwrap_or_default();
+    bail!("{} returned {}: {}", url, status, body.trim())
+}
+
+async fn send_json(request: RequestBuilder) -> Result<Value> {
+    let response = check(request.send().await?).await?;
+    Ok(response.json().await.unwrap_or(Value::Null))
+}
+
+/// Like [`send_json`], but a 404 means "not there" rather than an error.
+async fn find_json(request: RequestBuilder) -> Result<Option<Value>> {
+    let response = request.send().await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(check(response).await?.json().await?))
+}
+
+fn github(request: RequestBuilder, config: &ForgeConfig) -> RequestBuilder {
+    let request = request
+        .header("Accept", "application/vnd.github+json")
+        .header("X-GitHub-Api-Version", "2022-11-28")
+        .header("User-Agent", "gitscribe");
+    match &config.token {
+        Some(token) => request.header("Authorization", format!("Bearer {}", token)),
+        None => request,
+    }
+}
+
+async fn publish_github(
+    client: &Client,
+    config: &ForgeConfig,
+    draft: &ReleaseDraft,
+    dry_run: bool,
+) -> Result<PublishOutcome> {
+    let base = format!(
+        "{}/repos/{}",
+        config.api_url.trim_end_matches('/'),
+        config.repo
+    );
+    let existing = find_json(github(
+        client.get(format!(
+            "{}/releases/tags/{}",
+            base,
+            encode_segment(&draft.tag)
+        )),
+        config,
+    ))
+    .await?;
+
+    let mut outcome = PublishOutcome {
+        action: if existing.is_some() {
+            PublishAction::Update
+        } else {
+            PublishAction::Create
+        },
+        dry_run,
+        repo: config.repo.clone(),
+        tag: draft.tag.clone(),
+        url: existing
+            .as_ref()
+            .and_then(|r| r["html_url"].as_str())
+            .map(str::to_string),
+        attachment: draft.attachment.as_ref().map(|a| a.file_name.clone()),
+    };
+    if dry_run {
+        return Ok(outcome);
+    }
+
+    let release = match &existing {
+        Some(release) => {
+            let id = release["id"].as_u64().context("Release has no id")?;
+            let body = json!({ "name": draft.name, "body": draft.body });
+            send_json(github(
+                client
+                    .patch(format!("{}/releases/{}", base, id))
+                    .json(&body),
+                config,
+            ))
+            .await?
+        }
+        None => {
+            let body = json!({ "tag_name": draft.tag, "name": draft.name, "body": draft.body });
+            send_json(github(
+                client.post(format!("{}/releases", base)).json(&body),
+                config,
+            ))
+            .await?
+        }
+    };
+    outcome.url = release["html_url"].as_str().map(str::to_string);
+
+    if let Some(attachment) = &draft.attachment {
+        // Replace an asset of the same name left by an earlier run
+        let assets = release["assets"].as_array().cloned().unwrap_or_default();
+        for asset in assets
+            .iter()
+            .filter(|a| a["name"].as_str() == Some(&attachment.file_name))
+        {
+            if let Some(id) = asset["id"].as_u64() {
+                check(
+                    github(
+                        client.delete(format!("{}/releases/assets/{}", base, id)),
+                        config,
+                    )
+                    .send()
+                    .await?,
+                )
+                .await?;
+            }
+        }
+        let upload_url = release["upload_url"]
+            .as_str()
+            .context("Release has no upload URL")?;
+        // The URL is a template like ".../assets{?name,label}"
+        let upload_url = upload_url.split('{').next().unwrap_or(upload_url);
+        check(
+            github(
+                client
+                    .post(format!(
+                        "{}?name={}",
+                        upload_url,
+                        encode_segment(&attachment.file_name)
+                    ))
+                    .header("Content-Type", "application/octet-stream")
+                    .body(attachment.content.clone()),
+                config,
+            )
+            .send()
+            .await?,
+        )
+        .await?;
+    }
+    Ok(outcome)
+}
+
+fn gitlab(request: RequestBuilder, config: &ForgeConfig) -> RequestBuilder {
+    match &config.token {
+        Some(token) => request.header("PRIVATE-TOKEN", token),
+        None => request,
+    }
+}
+
+async fn publish_gitlab(
+    client: &Client,
+    config: &ForgeConfig,
+    draft: &ReleaseDraft,
+    dry_run: bool,
+) -> Result<PublishOutcome> {
+    let api = config.api_url.trim_end_matches('/');
+    let base = format!("{}/projects/{}", api, encode_segment(&config.repo));
+    let release_url = format!("{}/releases/{}", base, encode_segment(&draft.tag));
+    let existing = find_json(gitlab(client.get(&release_url), config)).await?;
+
+    let mut outcome = PublishOutcome {
+        action: if existing.is_some() {
+            PublishAction::Update
+        } else {
+            PublishAction::Create
+        },
+        dry_run,
+        repo: config.repo.clone(),
+        tag: draft.tag.clone(),
+        url: existing
+            .as_ref()
+            .and_then(|r| r["_links"]["self"].as_str())
+            .map(str::to_string),
+        attachment: draft.attachment.as_ref().map(|a| a.file_name.clone()),
+    };
+    if dry_run {
+        return Ok(outcome);
+    }
+
+    let release = if existing.is_some() {
+        let body = json!({ "name": draft.name, "description": draft.body });
+        send_json(gitlab(client.put(&release_url).json(&body), config)).await?
+    } else {
+        let body = json!({ "tag_name": draft.tag, "name": draft.name, "description": draft.body });
+        send_json(gitlab(
+            client.post(format!("{}/releases", base)).json(&body),
+            config,
+        ))
+        .await?
+    };
+    outcome.url = release["_links"]["self"].as_str().map(str::to_string);
+
+    if let Some(attachment) = &draft.attachment {
+        let form = multipart::Form::new().part(
+            "file",
+            multipart::Part::bytes(attachment.content.clone())
+                .file_name(attachment.file_name.clone()),
+        );
+        let upload = send_json(gitlab(
+            client.post(format!("{}/uploads", base)).multipart(form),
+            config,
+        ))
+        .await?;
+        // Upload paths are relative to the project's web page
+        let web_root = api.trim_end_matches("/api/v4");
+        let link_url = match upload["full_path"].as_str() {
+            Some(full_path) => format!("{}{}", web_root, full_path),
+            None => format!(
+                "{}/{}{}",
+                web_root,
+                config.repo,
+                upload["url"]
+                    .as_str()
+                    .context("Upload response has no URL")?
+            ),
+        };
+
+        let links_url = format!("{}/assets/links", release_url);
+        let links = send_json(gitlab(client.get(&links_url), config)).await?;
+        for link in links
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|l| l["name"].as_str() == Some(&attachment.file_name))
+        {
+            if let Some(id) = link["id"].as_u64() {
+                check(
+                    gitlab(client.delete(format!("{}/{}", links_url, id)), config)
+                        .send()
+                        .await?,
+                )
+                .await?;
+            }
+        }
+        let link = json!({ "name": attachment.file_name, "url": link_url });
+        send_json(gitlab(client.post(&links_url).json(&link), config)).await?;
+    }
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server;
+
+    fn draft() -> ReleaseDraft {
+        ReleaseDraft {
+            tag: "v1.0".to_string(),
+            name: "Release 1.0".to_string(),
+            body: "- Fixed the thing".to_string(),
+            attachment: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn creates_github_release_on_configured_api_url() {
+        let (api_url, received) = test_server::serve(vec![
+            ("GET /repos/owner/name/releases/tags/v1.0 ", 404, json!({})),
+            (
+                "POST /repos/owner/name/releases ",
+                201,
+                json!({ "id": 7, "html_url": "https://example.test/releases/7", "assets": [] }),
+            ),
+        ])
+        .await;
+        let config = ForgeConfig {
+            kind: ForgeKind::GitHub,
+            api_url,
+            repo: "owner/name".to_string(),
+            token: Some("secret".to_string()),
+        };
+
+        let outcome = publish_release(&config, &draft(), false).await.unwrap();
+
+        assert_eq!(outcome.action, PublishAction::Create);
+        assert_eq!(
+            outcome.url.as_deref(),
+            Some("https://example.test/releases/7")
+        );
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert!(received[1]
+            .head
+            .to_ascii_lowercase()
+            .contains("authorization: bearer secret"));
+        let body: Value = serde_json::from_str(&received[1].body).unwrap();
+        assert_eq!(body["tag_name"], "v1.0");
+        assert_eq!(body["name"], "Release 1.0");
+        assert_eq!(body["body"], "- Fixed the thing");
+    }
+
+    #[tokio::test]
+    async fn gitlab_dry_run_only_looks_the_release_up() {
+        let (api_url, received) = test_server::serve(vec![(
+            "GET /projects/group%2Fproject/releases/v1.0 ",
+            200,
+            json!({ "_links": { "self": "https://example.test/group/project/-/releases/v1.0" } }),
+        )])
+        .await;
+        let config = ForgeConfig {
+            kind: ForgeKind::GitLab,
+            api_url,
+            repo: "group/project".to_string(),
+            token: None,
+        };
+
+        let outcome = publish_release(&config, &draft(), true).await.unwrap();
+
+        assert_eq!(outcome.action, PublishAction::Update);
+        assert!(outcome.dry_run);
+        assert_eq!(
+            outcome.url.as_deref(),
+            Some("https://example.test/group/project/-/releases/v1.0")
+        );
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,913 @@
+use anyhow::{bail, Result};
+use pulldown_cmark::{html, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+use crate::structured::NotesFormat;
+
+/// Stylesheet embedded in HTML notes. Every rule is scoped to
+/// `.release-notes`, so the output can be pasted into a page or email
+/// without restyling its surroundings.
+pub const DEFAULT_HTML_THEME: &str = include_str!("../themes/release-notes.css");
+
+fn parser(markdown: &str) -> Parser<'_> {
+    Parser::new_ext(
+        markdown,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS,
+    )
+}
+
+/// Converts Markdown notes into `format`. `css` replaces the default HTML
+/// theme; pass an empty string for unstyled HTML.
+pub fn render_markdown(markdown: &str, format: NotesFormat, css: Option<&str>) -> Result<String> {
+    Ok(match format {
+        NotesFormat::Markdown => markdown.to_string(),
+        NotesFormat::Html => to_html(markdown, css),
+        NotesFormat::AsciiDoc => to_asciidoc(markdown),
+        NotesFormat::Rst => to_rst(markdown),
+        NotesFormat::Text => to_plain_text(markdown),
+        NotesFormat::Json => bail!("JSON output is only available for structured notes"),
+    })
+}
+
+/// Wraps an `<article class="release-notes">` element with its stylesheet.
+pub fn with_theme(article: &str, css: Option<&str>) -> String {
+    match css.unwrap_or(DEFAULT_HTML_THEME).trim() {
+        "" => article.to_string(),
+        css => format!("<style>\n{}\n</style>\n{}", css, article),
+    }
+}
+
+/// Converts Markdown into a themed HTML article. Raw HTML in the notes is
+/// escaped, so model output cannot inject markup into pages or emails.
+pub fn to_html(markdown: &str, css: Option<&str>) -> String {
+    let events = parser(markdown).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        event => event,
+    });
+    let mut body = String::new();
+    html::push_html(&mut body, events);
+    with_theme(
+        &format!("<article class=\"release-notes\">\n{}</article>\n", body),
+        css,
+    )
+}
+
+pub fn to_asciidoc(markdown: &str) -> String {
+    TextWriter::new(&AsciiDoc).write(markdown)
+}
+
+pub fn to_rst(markdown: &str) -> String {
+    TextWriter::new(&Rst).write(markdown)
+}
+
+pub fn to_plain_text(markdown: &str) -> String {
+    TextWriter::new(&PlainText).write(markdown)
+}
+
+/// Converts Markdown into Confluence storage format (XHTML). Code blocks
+/// become code macros, images `ac:image` elements, and raw HTML is escaped
+/// so it cannot break the page.
+pub fn to_confluence_storage(markdown: &str) -> String {
+    let mut events = Vec::new();
+    let mut code: Option<(String, String)> = None;
+    let mut image: Option<(String, String)> = None;
+    for event in parser(markdown) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(lang) => {
+                        lang.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((lang, String::new()));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((lang, body)) = code.take() {
+                    events.push(Event::Html(code_macro(&lang, &body).into()));
+                }
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                image = Some((dest_url.to_string(), String::new()));
+            }
+            Event::End(TagEnd::Image) => {
+                if let Some((url, alt)) = image.take() {
+                    events.push(Event::Html(
+                        format!(
+                            "<ac:image ac:alt=\"{}\"><ri:url ri:value=\"{}\" /></ac:image>",
+                            escape_xml(&alt),
+                            escape_xml(&url)
+                        )
+                        .into(),
+                    ));
+                }
+            }
+            Event::Text(text) | Event::Code(text) if code.is_some() || image.is_some() => {
+                // Code block body or image alt text
+                if let Some((_, buffer)) = code.as_mut().or(image.as_mut()) {
+                    buffer.push_str(&text);
+                }
+            }
+            Event::Html(raw) | Event::InlineHtml(raw) => events.push(Event::Text(raw)),
+            event => events.push(event),
+        }
+    }
+    let mut storage = String::new();
+    html::push_html(&mut storage, events.into_iter());
+    storage
+}
+
+fn code_macro(lang: &str, code: &str) -> String {
+    let language = if lang.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<ac:parameter ac:name=\"language\">{}</ac:parameter>",
+            escape_xml(lang)
+        )
+    };
+    format!(
+        "<ac:structured-macro ac:name=\"code\">{}<ac:plain-text-body><![CDATA[{}]]></ac:plain-text-body></ac:structured-macro>\n",
+        language,
+        // "]]>" would end the CDATA section early
+        code.trim_end_matches('\n').replace("]]>", "]]]]><![CDATA[>")
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Markup of a line-oriented text format.
+trait Dialect {
+    /// Escapes characters that would otherwise be read as inline markup.
+    fn escape(&self, text: &str) -> String;
+    /// Protects a paragraph whose first characters would start a block,
+    /// e.g. a list or a comment.
+    fn paragraph(&self, text: String) -> String {
+        text
+    }
+    fn heading(&self, level: usize, text: &str) -> String;
+    fn strong(&self) -> &'static str;
+    fn emphasis(&self) -> &'static str;
+    fn strikethrough(&self) -> (&'static str, &'static str);
+    fn code(&self, code: &str) -> String;
+    fn link(&self, text: &str, url: &str) -> String;
+    fn hard_break(&self) -> &'static str;
+    /// Marker for a list item at `depth` (0 for top level).
+    fn item_marker(&self, depth: usize, number: Option<u64>) -> String;
+    /// Prefix for further lines and paragraphs of a list item.
+    fn item_indent(&self, marker: &str) -> String;
+    /// Whether nested lists need a blank line before and after them.
+    fn spaced_nested_lists(&self) -> bool;
+    fn quote(&self, text: &str) -> String;
+    fn code_block(&self, lang: &str, code: &str) -> String;
+    fn rule(&self) -> &'static str;
+    fn table(&self, rows: &[Vec<String>]) -> String;
+}
+
+fn indent_lines(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Whether the characters on both sides of `chars[i]` are letters or digits,
+/// where single formatting marks have no effect.
+fn inside_word(chars: &[char], i: usize) -> bool {
+    let word = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric());
+    i > 0 && word(chars.get(i - 1)) && word(chars.get(i + 1))
+}
+
+/// Whether `text` starts like a character reference such as `&amp;`.
+fn starts_with_reference(text: &str) -> bool {
+    let name = text.strip_prefix('#').unwrap_or(text);
+    let len = name
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(name.len());
+    len > 0 && name[len..].starts_with(';')
+}
+
+/// Whether `text` starts like an item of a numbered list, e.g. "1. " or "2)".
+fn starts_enumerated(text: &str) -> bool {
+    let rest = text.trim_start_matches(|c: char| c.is_ascii_digit());
+    rest.len() < text.len() && rest.starts_with(['.', ')'])
+}
+
+/// Block macros and admonitions that AsciiDoc recognises at the start of a
+/// paragraph.
+const ASCIIDOC_PARAGRAPH_PREFIXES: &[&str] = &[
+    "NOTE:",
+    "TIP:",
+    "IMPORTANT:",
+    "WARNING:",
+    "CAUTION:",
+    "image::",
+    "include::",
+];
+
+struct AsciiDoc;
+
+impl Dialect for AsciiDoc {
+    fn escape(&self, text: &str) -> String {
+        // Character references survive every AsciiDoc substitution
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::with_capacity(text.len());
+        for (i, (at, c)) in text.char_indices().enumerate() {
+            let escape = match c {
+                '*' | '_' | '#' | '+' => !inside_word(&chars, i),
+                '`' | '^' | '~' | '[' | ']' | '{' | '}' | '|' | '<' => true,
+                '&' => starts_with_reference(&text[at + 1..]),
+                // "term::" starts a description list
+                ':' => chars.get(i + 1) == Some(&':'),
+                _ => false,
+            };
+            if escape {
+                out.push_str(&format!("&#{};", c as u32));
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+    fn paragraph(&self, text: String) -> String {
+        let block_start = text.starts_with(['.', '=', '-', '/', ':', '\'', '>'])
+            || starts_enumerated(&text)
+            || ASCIIDOC_PARAGRAPH_PREFIXES
+                .iter()
+                .any(|p| text.starts_with(p));
+        if block_start {
+            // {empty} is a built-in attribute that renders as nothing
+            format!("{{empty}}{}", text)
+        } else {
+            text
+        }
+    }
+    fn heading(&self, level: usize, text: &str) -> String {
+        format!("{} {}", "=".repeat(level), text)
+    }
+    fn strong(&self) -> &'static str {
+        "*"
+    }
+    fn emphasis(&self) -> &'static str {
+        "_"
+    }
+    fn strikethrough(&self) -> (&'static str, &'static str) {
+        ("[.line-through]#", "#")
+    }
+    fn code(&self, code: &str) -> String {
+        format!("`+{}+`", code)
+    }
+    fn link(&self, text: &str, url: &str) -> String {
+        format!("{}[{}]", url, text)
+    }
+    fn hard_break(&self) -> &'static str {
+        " +\n"
+    }
+    fn item_marker(&self, depth: usize, number: Option<u64>) -> String {
+        let marker = if number.is_some() { "." } else { "*" };
+        format!("{} ", marker.repeat(depth + 1))
+    }
+    fn item_indent(&self, _marker: &str) -> String {
+        String::new()
+    }
+    fn spaced_nested_lists(&self) -> bool {
+        false
+    }
+    fn quote(&self, text: &str) -> String {
+        format!("____\n{}\n____", text)
+    }
+    fn code_block(&self, lang: &str, code: &str) -> String {
+        let header = if lang.is_empty() {
+            String::new()
+        } else {
+            format!("[source,{}]\n", lang)
+        };
+        // The delimiter must be longer than any dashed line in the code
+        let longest = code
+            .lines()
+            .filter(|line| !line.is_empty() && line.chars().all(|c| c == '-'))
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let delimiter = "-".repeat(longest.max(3) + 1);
+        format!("{1}{0}\n{2}\n{0}", delimiter, header, code.trim_end())
+    }
+    fn rule(&self) -> &'static str {
+        "'''"
+    }
+    fn table(&self, rows: &[Vec<String>]) -> String {
+        let mut out = String::from("[options=\"header\"]\n|===\n");
+        for row in rows {
+            let cells: Vec<String> = row.iter().map(|c| format!("| {}", c)).collect();
+            out.push_str(&cells.join(" "));
+            out.push('\n');
+        }
+        out.push_str("|===");
+        out
+    }
+}
+
+struct Rst;
+
+impl Dialect for Rst {
+    fn escape(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::with_capacity(text.len());
+        for (i, &c) in chars.iter().enumerate() {
+            let escape = match c {
+                '\\' | '*' | '`' | '|' => true,
+                // A trailing underscore makes a reference, as in `name_`
+                '_' => !chars.get(i + 1).is_some_and(|c| c.is_alphanumeric()),
+                _ => false,
+            };
+            if escape {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out
+    }
+    fn paragraph(&self, text: String) -> String {
+        // A paragraph ending in "::" would turn the next block into a literal one
+        let text = match text.strip_suffix("::") {
+            Some(rest) => format!("{}:\\:", rest),
+            None => text,
+        };
+        let marker = text.starts_with(|c: char| c.is_ascii_punctuation() && c != '\\');
+        if marker || starts_enumerated(&text) {
+            format!("\\{}", text)
+        } else {
+            text
+        }
+    }
+    fn heading(&self, level: usize, text: &str) -> String {
+        let width = text.chars().count();
+        match level {
+            1 => format!("{0}\n{1}\n{0}", "=".repeat(width), text),
+            level => {
+                let underline = ['=', '-', '~', '^', '"'][(level - 2).min(4)];
+                format!("{}\n{}", text, underline.to_string().repeat(width))
+            }
+        }
+    }
+    fn strong(&self) -> &'static str {
+        "**"
+    }
+    fn emphasis(&self) -> &'static str {
+        "*"
+    }
+    fn strikethrough(&self) -> (&'static str, &'static str) {
+        ("", "")
+    }
+    fn code(&self, code: &str) -> String {
+        format!("``{}``", code)
+    }
+    fn link(&self, text: &str, url: &str) -> String {
+        format!("`{} <{}>`__", text, url)
+    }
+    fn hard_break(&self) -> &'static str {
+        "\n"
+    }
+    fn item_marker(&self, depth: usize, number: Option<u64>) -> String {
+        let marker = match number {
+            Some(n) => format!("{}. ", n),
+            None => "- ".to_string(),
+        };
+        format!("{}{}", "  ".repeat(depth), marker)
+    }
+    fn item_indent(&self, marker: &str) -> String {
+        " ".repeat(marker.chars().count())
+    }
+    fn spaced_nested_lists(&self) -> bool {
+        true
+    }
+    fn quote(&self, text: &str) -> String {
+        // The empty comment keeps the indented quote from joining a preceding block
+        format!("..\n\n{}", indent_lines(text, "    "))
+    }
+    fn code_block(&self, lang: &str, code: &str) -> String {
+        let directive = if lang.is_empty() {
+            "::".to_string()
+        } else {
+            format!(".. code-block:: {}", lang)
+        };
+        format!("{}\n\n{}", directive, indent_lines(code.trim_end(), "    "))
+    }
+    fn rule(&self) -> &'static str {
+        "----"
+    }
+    fn table(&self, rows: &[Vec<String>]) -> String {
+        let mut out = String::from(".. list-table::\n   :header-rows: 1\n\n");
+        for row in rows {
+            for (i, cell) in row.iter().enumerate() {
+                let marker = if i == 0 { "   * - " } else { "     - " };
+                out.push_str(marker);
+                out.push_str(cell);
+                out.push('\n');
+            }
+        }
+        out.trim_end().to_string()
+    }
+}
+
+struct PlainText;
+
+impl Dialect for PlainText {
+    fn escape(&self, text: &str) -> String {
+        text.to_string()
+    }
+    fn heading(&self, level: usize, text: &str) -> String {
+        let width = text.chars().count();
+        match level {
+            1 => format!("{}\n{}", text, "=".repeat(width)),
+            2 => format!("{}\n{}", text, "-".repeat(width)),
+            _ => text.to_string(),
+        }
+    }
+    fn strong(&self) -> &'static str {
+        ""
+    }
+    fn emphasis(&self) -> &'static str {
+        ""
+    }
+    fn strikethrough(&self) -> (&'static str, &'static str) {
+        ("", "")
+    }
+    fn code(&self, code: &str) -> String {
+        code.to_string()
+    }
+    fn link(&self, text: &str, url: &str) -> String {
+        if text == url {
+            url.to_string()
+        } else {
+            format!("{} ({})", text, url)
+        }
+    }
+    fn hard_break(&self) -> &'static str {
+        "\n"
+    }
+    fn item_marker(&self, depth: usize, number: Option<u64>) -> String {
+        let marker = match number {
+            Some(n) => format!("{}. ", n),
+            None => "- ".to_string(),
+        };
+        format!("{}{}", "  ".repeat(depth), marker)
+    }
+    fn item_indent(&self, marker: &str) -> String {
+        " ".repeat(marker.chars().count())
+    }
+    fn spaced_nested_lists(&self) -> bool {
+        false
+    }
+    fn quote(&self, text: &str) -> String {
+        indent_lines(text, "    ")
+    }
+    fn code_block(&self, _lang: &str, code: &str) -> String {
+        indent_lines(code.trim_end(), "    ")
+    }
+    fn rule(&self) -> &'static str {
+        "----------------------------------------"
+    }
+    fn table(&self, rows: &[Vec<String>]) -> String {
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let widths: Vec<usize> = (0..columns)
+            .map(|i| {
+                rows.iter()
+                    .filter_map(|row| row.get(i))
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let mut lines = Vec::new();
+        for (r, row) in rows.iter().enumerate() {
+            let cells: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| format!("{:<width$}", cell, width = widths[i]))
+                .collect();
+            lines.push(cells.join(" | ").trim_end().to_string());
+            if r == 0 {
+                let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+                lines.push(rule.join("-+-"));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// An open list: the next item number for ordered lists, the current
+/// item's marker and whether its first line has been written.
+struct ListState {
+    next_number: Option<u64>,
+    marker: String,
+    item_started: bool,
+}
+
+/// Walks Markdown events and writes them using a [`Dialect`].
+struct TextWriter<'d> {
+    dialect: &'d dyn Dialect,
+    blocks: Vec<String>,
+    /// Inline text being collected; links and table cells push a level.
+    inline: Vec<String>,
+    /// Adjacent text events, escaped together once the run ends.
+    pending: String,
+    links: Vec<String>,
+    lists: Vec<ListState>,
+    /// Blocks collected inside block quotes, innermost last.
+    quotes: Vec<Vec<String>>,
+    code: Option<(String, String)>,
+    table: Vec<Vec<String>>,
+    row: Vec<String>,
+}
+
+impl<'d> TextWriter<'d> {
+    fn new(dialect: &'d dyn Dialect) -> Self {
+        Self {
+            dialect,
+            blocks: Vec::new(),
+            inline: vec![String::new()],
+            pending: String::new(),
+            links: Vec::new(),
+            lists: Vec::new(),
+            quotes: Vec::new(),
+            code: None,
+            table: Vec::new(),
+            row: Vec::new(),
+        }
+    }
+
+    fn write(mut self, markdown: &str) -> String {
+        for event in parser(markdown) {
+            self.event(event);
+        }
+        self.flush_pending();
+        self.flush_text();
+        let mut out = join_blocks(&self.blocks);
+        out.push('\n');
+        out
+    }
+
+    fn push_inline(&mut self, text: &str) {
+        if let Some((_, code)) = &mut self.code {
+            code.push_str(text);
+        } else if let Some(current) = self.inline.last_mut() {
+            current.push_str(text);
+        }
+    }
+
+    /// Escapes and writes the text collected from consecutive text events.
+    /// The parser splits text at would-be delimiters, so escaping the whole
+    /// run sees every character's neighbours.
+    fn flush_pending(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let text = self.dialect.escape(&std::mem::take(&mut self.pending));
+        self.push_inline(&text);
+    }
+
+    fn take_inline(&mut self) -> String {
+        self.inline
+            .last_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    }
+
+    /// Adds a finished block to the innermost quote or the document.
+    fn push_block(&mut self, block: String) {
+        let target = self.quotes.last_mut().unwrap_or(&mut self.blocks);
+        target.push(block);
+    }
+
+    /// Adds a line to the list block being written.
+    fn push_list_line(&mut self, line: &str) {
+        let target = self.quotes.last_mut().unwrap_or(&mut self.blocks);
+        if let Some(list) = target.last_mut() {
+            if !list.is_empty() {
+                list.push('\n');
+            }
+            list.push_str(line);
+        }
+    }
+
+    fn append_to_item(&mut self, text: String) {
+        let dialect = self.dialect;
+        let list = self.lists.last_mut().expect("inside a list");
+        let indent = dialect.item_indent(&list.marker);
+        let mut lines = text.lines();
+        let first = lines.next().unwrap_or_default();
+        let mut item = if list.item_started {
+            format!("{}{}", indent, first)
+        } else {
+            list.item_started = true;
+            format!("{}{}", list.marker, first)
+        };
+        for line in lines {
+            item.push('\n');
+            item.push_str(&indent);
+            item.push_str(line);
+        }
+        self.push_list_line(&item);
+    }
+
+    /// Writes text collected so far, e.g. the words of a tight list item
+    /// before its nested list starts.
+    fn flush_text(&mut self) {
+        let text = self.take_inline();
+        if text.is_empty() {
+            return;
+        }
+        let text = self.dialect.paragraph(text);
+        if self.lists.is_empty() {
+            self.push_block(text);
+        } else {
+            self.append_to_item(text);
+        }
+    }
+
+    fn event(&mut self, event: Event<'_>) {
+        let d = self.dialect;
+        let is_text = matches!(
+            event,
+            Event::Text(_) | Event::Html(_) | Event::InlineHtml(_)
+        );
+        if !is_text {
+            self.flush_pending();
+        }
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            // Code block bodies are literal; raw HTML is shown as text
+            Event::Text(text) if self.code.is_some() => self.push_inline(&text),
+            Event::Text(text) | Event::Html(text) | Event::InlineHtml(text) => {
+                self.pending.push_str(&text)
+            }
+            Event::Code(code) => self.push_inline(&d.code(&code)),
+            Event::SoftBreak => self.push_inline(" "),
+            Event::HardBreak => self.push_inline(d.hard_break()),
+            Event::Rule => {
+                self.flush_text();
+                self.push_block(d.rule().to_string());
+            }
+            Event::TaskListMarker(done) => self.push_inline(if done { "[x] " } else { "[ ] " }),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'_>) {
+        let d = self.dialect;
+        match tag {
+            Tag::Strong => self.push_inline(d.strong()),
+            Tag::Emphasis => self.push_inline(d.emphasis()),
+            Tag::Strikethrough => self.push_inline(d.strikethrough().0),
+            Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } => {
+                self.links.push(dest_url.to_string());
+                self.inline.push(String::new());
+            }
+            Tag::List(start) => {
+                self.flush_text();
+                // The whole list, nested lists included, is one block
+                if self.lists.is_empty() {
+                    self.push_block(String::new());
+                } else if d.spaced_nested_lists() {
+                    self.push_list_line("");
+                }
+                self.lists.push(ListState {
+                    next_number: start,
+                    marker: String::new(),
+                    item_started: false,
+                });
+            }
+            Tag::Item => {
+                let depth = self.lists.len() - 1;
+                let list = self.lists.last_mut().expect("inside a list");
+                list.marker = d.item_marker(depth, list.next_number);
+                list.item_started = false;
+                if let Some(n) = &mut list.next_number {
+                    *n += 1;
+                }
+            }
+            Tag::BlockQuote(_) => {
+                self.flush_text();
+                self.quotes.push(Vec::new());
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush_text();
+                let lang = match kind {
+                    CodeBlockKind::Fenced(lang) => lang.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((lang, String::new()));
+            }
+            Tag::Table(_) => {
+                self.flush_text();
+                self.table.clear();
+            }
+            Tag::TableCell => self.inline.push(String::new()),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        let d = self.dialect;
+        match tag {
+            TagEnd::Strong => self.push_inline(d.strong()),
+            TagEnd::Emphasis => self.push_inline(d.emphasis()),
+            TagEnd::Strikethrough => self.push_inline(d.strikethrough().1),
+            TagEnd::Link | TagEnd::Image => {
+                let text = self.inline.pop().unwrap_or_default();
+                let url = self.links.pop().unwrap_or_default();
+                self.push_inline(&d.link(&text, &url));
+            }
+            TagEnd::Paragraph | TagEnd::Item => self.flush_text(),
+            TagEnd::Heading(level) => {
+                let text = self.take_inline();
+                self.push_block(d.heading(heading_level(level), &text));
+            }
+            TagEnd::List(_) => {
+                self.flush_text();
+                self.lists.pop();
+                if !self.lists.is_empty() && d.spaced_nested_lists() {
+                    self.push_list_line("");
+                }
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush_text();
+                let blocks = self.quotes.pop().unwrap_or_default();
+                let text = join_blocks(&blocks);
+                self.push_block(d.quote(&text));
+            }
+            TagEnd::CodeBlock => {
+                if let Some((lang, code)) = self.code.take() {
+                    self.push_block(d.code_block(&lang, &code));
+                }
+            }
+            TagEnd::TableCell => {
+                let cell = self.inline.pop().unwrap_or_default();
+                self.row.push(cell.trim().to_string());
+            }
+            TagEnd::TableHead | TagEnd::TableRow => {
+                let row = std::mem::take(&mut self.row);
+                self.table.push(row);
+            }
+            TagEnd::Table => {
+                let rows = std::mem::take(&mut self.table);
+                self.push_block(d.table(&rows));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn join_blocks(blocks: &[String]) -> String {
+    blocks
+        .iter()
+        .map(|b| b.trim_end())
+        .filter(|b| !b.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn heading_level(level: HeadingLevel) -> usize {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTES: &str =
+        "# Release 2.0\n\n## Fixes\n\n- Export works\n  - Nested detail\n- Second\n";
+
+    #[test]
+    fn asciidoc_headings_and_nested_lists() {
+        assert_eq!(
+            to_asciidoc(NOTES),
+            "= Release 2.0\n\n== Fixes\n\n* Export works\n** Nested detail\n* Second\n"
+        );
+        assert_eq!(to_asciidoc("1. One\n2. Two\n"), ". One\n. Two\n");
+    }
+
+    #[test]
+    fn asciidoc_code_links_and_tables() {
+        assert_eq!(
+            to_asciidoc("Run `make *all*` or see [the docs](https://example.com/a_b).\n"),
+            "Run `+make *all*+` or see https://example.com/a_b[the docs].\n"
+        );
+        assert_eq!(
+            to_asciidoc("```sh\necho hi\n----\n```\n"),
+            "[source,sh]\n-----\necho hi\n----\n-----\n"
+        );
+        assert_eq!(
+            to_asciidoc("| Key | Note |\n| --- | --- |\n| A | x\\|y |\n"),
+            "[options=\"header\"]\n|===\n| Key | Note\n| A | x&#124;y\n|===\n"
+        );
+    }
+
+    #[test]
+    fn asciidoc_escapes_markup_in_text() {
+        assert_eq!(
+            to_asciidoc("Keep snake_case, but escape _x, #tag, a+b+, 2^10^, {attr} and &amp;copy;; R&D stays.\n"),
+            "Keep snake_case, but escape &#95;x, &#35;tag, a+b&#43;, 2&#94;10&#94;, &#123;attr&#125; and &#38;copy;; R&D stays.\n"
+        );
+        assert_eq!(to_asciidoc("Term\\:: here\n"), "Term&#58;: here\n");
+        assert_eq!(
+            to_asciidoc("NOTE: plain text\n\n\\. not a list\n"),
+            "{empty}NOTE: plain text\n\n{empty}. not a list\n"
+        );
+    }
+
+    #[test]
+    fn rst_headings_and_nested_lists() {
+        assert_eq!(
+            to_rst(NOTES),
+            "===========\nRelease 2.0\n===========\n\nFixes\n=====\n\n- Export works\n\n  - Nested detail\n\n- Second\n"
+        );
+        assert_eq!(to_rst("1. One\n2. Two\n"), "1. One\n2. Two\n");
+    }
+
+    #[test]
+    fn rst_code_links_and_tables() {
+        assert_eq!(
+            to_rst("Run `make *all*` or see [the docs](https://example.com).\n"),
+            "Run ``make *all*`` or see `the docs <https://example.com>`__.\n"
+        );
+        assert_eq!(
+            to_rst("```sh\necho hi\n```\n"),
+            ".. code-block:: sh\n\n    echo hi\n"
+        );
+        assert_eq!(
+            to_rst("| Key | Note |\n| --- | --- |\n| A | x\\|y |\n"),
+            ".. list-table::\n   :header-rows: 1\n\n   * - Key\n     - Note\n   * - A\n     - x\\|y\n"
+        );
+    }
+
+    #[test]
+    fn rst_escapes_markup_in_text() {
+        assert_eq!(
+            to_rst("Keep snake_case, but escape name_, a|b|, \\*x\\* and \\`y\\`.\n"),
+            "Keep snake_case, but escape name\\_, a\\|b\\|, \\*x\\* and \\`y\\`.\n"
+        );
+        assert_eq!(
+            to_rst("\\.. not a comment\n\n1\\. not a list\n\nends with::\n"),
+            "\\.. not a comment\n\n\\1. not a list\n\nends with:\\:\n"
+        );
+        assert_eq!(to_rst("- \\- nested marker\n"), "- \\- nested marker\n");
+    }
+
+    #[test]
+    fn plain_text_drops_markup() {
+        assert_eq!(
+            to_plain_text(NOTES),
+            "Release 2.0\n===========\n\nFixes\n-----\n\n- Export works\n  - Nested detail\n- Second\n"
+        );
+        assert_eq!(
+            to_plain_text(
+                "Run `make` and see [docs](https://example.com).\n\n```\ncode *here*\n```\n"
+            ),
+            "Run make and see docs (https://example.com).\n\n    code *here*\n"
+        );
+        assert_eq!(
+            to_plain_text("| Key | Note |\n| --- | --- |\n| Alpha | x |\n"),
+            "Key   | Note\n------+-----\nAlpha | x\n"
+        );
+    }
+
+    #[test]
+    fn html_escapes_raw_html() {
+        let html = to_html(
+            "Fixed <b onclick=\"x()\">this</b>.\n\n<script>alert(1)</script>\n",
+            Some(""),
+        );
+        assert!(!html.contains("<script>"), "{}", html);
+        assert!(!html.contains("<b "), "{}", html);
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(html.contains("Fixed &lt;b onclick=\"x()\"&gt;this&lt;/b&gt;."));
+    }
+
+    #[test]
+    fn text_dialects_show_raw_html_as_text() {
+        assert_eq!(to_asciidoc("a <b>c</b>\n"), "a &#60;b>c&#60;/b>\n");
+        assert_eq!(to_plain_text("a <b>c</b>\n"), "a <b>c</b>\n");
+    }
+}
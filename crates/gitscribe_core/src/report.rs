@@ -0,0 +1,244 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::confluence::PageOutcome;
+use crate::git_notes::StoreOutcome;
+use crate::grounding::{GroundingReport, Source};
+use crate::jira::{FetchStats, JiraWriteOutcome};
+use crate::notify::NotifyOutcome;
+use crate::ollama::{OllamaOptions, OllamaStats};
+use crate::publish::PublishOutcome;
+use crate::redact::Redaction;
+use crate::translate::TranslationCheck;
+use crate::validate::Violation;
+
+/// Rough token count for budgeting; most tokenizers average ~4 characters per token.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Facts about the assembled context, collected by `generate_context_with_stats`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ContextStats {
+    pub commit_count: usize,
+    pub diff_files: usize,
+    pub diff_bytes: usize,
+    pub context_bytes: usize,
+    pub jira: Option<FetchStats>,
+    /// Jira issues found for the range's commits.
+    pub issue_keys: Vec<String>,
+    /// Secrets and personal data masked in the context.
+    pub redactions: Vec<Redaction>,
+    /// Where the style examples in the context came from, e.g. "v1.2.0 (git notes)".
+    pub style_examples: Vec<String>,
+    /// Commits and issues in the context, for checking the notes' grounding.
+    #[serde(skip)]
+    pub sources: Vec<Source>,
+}
+
+/// Summary of a single run, printed at the end of the CLI and shown in the GUI.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct GenerationReport {
+    pub start: String,
+    pub end: String,
+    /// Template name when several audiences were generated in one run.
+    pub audience: Option<String>,
+    pub model: Option<String>,
+    pub options: Option<OllamaOptions>,
+    pub context: ContextStats,
+    pub system_prompt_bytes: usize,
+    pub estimated_prompt_tokens: usize,
+    pub cache_hit: bool,
+    pub cancelled: bool,
+    pub ollama: Option<OllamaStats>,
+    pub context_ms: u64,
+    pub generation_ms: u64,
+    /// Template problems left in the final notes.
+    pub violations: Vec<Violation>,
+    /// Whether a repair pass rewrote the notes.
+    pub repaired: bool,
+    /// Which bullets could be traced back to commits, issues or notes.
+    pub grounding: Option<GroundingReport>,
+    /// Locales the notes were translated into.
+    pub translations: Vec<TranslationCheck>,
+    /// The forge release the notes were published to.
+    pub published: Option<PublishOutcome>,
+    /// The Confluence page the notes were published to.
+    pub confluence: Option<PageOutcome>,
+    /// Changes written back to Jira, or planned in a dry run.
+    pub jira_writes: Vec<JiraWriteOutcome>,
+    /// The chat webhook the summary was posted to.
+    pub notified: Option<NotifyOutcome>,
+    /// Where the notes were stored in the repository.
+    pub stored: Vec<StoreOutcome>,
+}
+
+impl GenerationReport {
+    /// Web page of the release or Confluence page published in this run.
+    pub fn published_url(&self) -> Option<&str> {
+        self.published
+            .as_ref()
+            .and_then(|p| p.url.as_deref())
+            .or(self.confluence.as_ref().and_then(|p| p.url.as_deref()))
+    }
+
+    pub fn new(start: &str, end: &str, context: &str, stats: ContextStats) -> Self {
+        Self {
+            start: start.to_string(),
+            end: end.to_string(),
+            estimated_prompt_tokens: estimate_tokens(context),
+            context: stats,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_system_prompt(&mut self, system_prompt: Option<&str>) {
+        let prompt = system_prompt.unwrap_or("");
+        self.system_prompt_bytes = prompt.len();
+        self.estimated_prompt_tokens += estimate_tokens(prompt);
+    }
+
+    /// Generated tokens per second, when Ollama reported its eval timings.
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        let stats = self.ollama.as_ref()?;
+        let count = stats.eval_count? as f64;
+        let nanos = stats.eval_duration? as f64;
+        (nanos > 0.0).then(|| count / (nanos / 1e9))
+    }
+
+    pub fn write_json(&self, path: &str) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(Path::new(path), content).context(format!("Failed to write report to {}", path))
+    }
+
+    /// Writes the reports of a multi-audience run as one JSON array.
+    pub fn write_json_list(reports: &[Self], path: &str) -> Result<()> {
+        let content = serde_json::to_string_pretty(reports)?;
+        fs::write(Path::new(path), content).context(format!("Failed to write report to {}", path))
+    }
+}
+
+impl fmt::Display for GenerationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(audience) = &self.audience {
+            writeln!(f, "Audience:       {}", audience)?;
+        }
+        writeln!(f, "Range:          {}..{}", self.start, self.end)?;
+        writeln!(
+            f,
+            "Commits:        {} ({} files changed, {} bytes of diff)",
+            self.context.commit_count, self.context.diff_files, self.context.diff_bytes
+        )?;
+        writeln!(
+            f,
+            "Prompt size:    {} bytes context + {} bytes system prompt (~{} tokens)",
+            self.context.context_bytes, self.system_prompt_bytes, self.estimated_prompt_tokens
+        )?;
+        if let Some(jira) = &self.context.jira {
+            writeln!(f, "Jira issues:    {}", jira)?;
+        }
+        if !self.context.style_examples.is_empty() {
+            writeln!(
+                f,
+                "Style examples: {}",
+                self.context.style_examples.join(", ")
+            )?;
+        }
+        if !self.context.redactions.is_empty() {
+            let redactions: Vec<String> = self
+                .context
+                .redactions
+                .iter()
+                .map(|r| r.to_string())
+                .collect();
+            writeln!(f, "Redacted:       {}", redactions.join("; "))?;
+        }
+        writeln!(f, "Context time:   {} ms", self.context_ms)?;
+        if let Some(model) = &self.model {
+            let source = if self.cache_hit {
+                " (cached result)"
+            } else if self.cancelled {
+                " (cancelled)"
+            } else {
+                ""
+            };
+            writeln!(f, "Model:          {}{}", model, source)?;
+            writeln!(f, "Generation:     {} ms", self.generation_ms)?;
+        }
+        if let Some(stats) = &self.ollama {
+            writeln!(
+                f,
+                "Ollama tokens:  {} prompt, {} generated",
+                stats.prompt_eval_count.unwrap_or(0),
+                stats.eval_count.unwrap_or(0)
+            )?;
+            if let Some(tps) = self.tokens_per_second() {
+                writeln!(f, "Throughput:     {:.1} tokens/s", tps)?;
+            }
+        }
+        if self.model.is_some() && !self.cancelled {
+            let repaired = if self.repaired { " after repair" } else { "" };
+            writeln!(
+                f,
+                "Validation:     {} problem(s){}",
+                self.violations.len(),
+                repaired
+            )?;
+        }
+        if let Some(grounding) = &self.grounding {
+            writeln!(
+                f,
+                "Grounding:      {}/{} bullets supported by commits, issues or notes",
+                grounding.supported_count(),
+                grounding.bullets.len()
+            )?;
+        }
+        for translation in &self.translations {
+            writeln!(
+                f,
+                "Translation:    {} ({} problem(s))",
+                translation.locale,
+                translation.problems.len()
+            )?;
+        }
+        if let Some(published) = &self.published {
+            writeln!(f, "Release:        {}", published)?;
+        }
+        if let Some(page) = &self.confluence {
+            writeln!(f, "Confluence:     {}", page)?;
+        }
+        if !self.jira_writes.is_empty() {
+            let count =
+                |f: fn(&&JiraWriteOutcome) -> bool| self.jira_writes.iter().filter(f).count();
+            let failed = count(|w| w.error.is_some());
+            let skipped = count(|w| w.skipped.is_some());
+            if self.jira_writes.iter().any(|w| w.dry_run) {
+                writeln!(
+                    f,
+                    "Jira writes:    {} planned (dry run), {} skipped, {} failed",
+                    self.jira_writes.len() - skipped - failed,
+                    skipped,
+                    failed
+                )?;
+            } else {
+                writeln!(
+                    f,
+                    "Jira writes:    {} applied, {} skipped, {} failed",
+                    count(|w| w.applied),
+                    skipped,
+                    failed
+                )?;
+            }
+        }
+        if let Some(notified) = &self.notified {
+            writeln!(f, "Notification:   {}", notified)?;
+        }
+        for stored in &self.stored {
+            writeln!(f, "Stored:         {}", stored)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,323 @@
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt::Write as _;
+use std::str::FromStr;
+use tokio_util::sync::CancellationToken;
+
+use crate::ollama::{call_ollama, OllamaOptions, OllamaStats};
+use crate::render;
+
+/// One bullet of the release notes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NoteItem {
+    pub title: String,
+    pub description: String,
+    /// Issue keys or CVE IDs backing this item, e.g. `PROJ-123`.
+    #[serde(default)]
+    pub references: Vec<String>,
+}
+
+/// Release notes as returned by the model in structured mode.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReleaseNotes {
+    #[serde(default)]
+    pub title: Option<String>,
+    pub summary: String,
+    #[serde(default)]
+    pub features: Vec<NoteItem>,
+    #[serde(default)]
+    pub improvements: Vec<NoteItem>,
+    #[serde(default)]
+    pub fixes: Vec<NoteItem>,
+    #[serde(default)]
+    pub security: Vec<NoteItem>,
+    #[serde(default)]
+    pub deprecations: Vec<NoteItem>,
+}
+
+/// Output formats for release notes. `Json` is only available for
+/// structured notes; the others are rendered from Markdown.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NotesFormat {
+    #[default]
+    Markdown,
+    Html,
+    AsciiDoc,
+    Rst,
+    Text,
+    Json,
+}
+
+impl NotesFormat {
+    /// File extension for default output names.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::AsciiDoc => "adoc",
+            Self::Rst => "rst",
+            Self::Text => "txt",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl FromStr for NotesFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            "asciidoc" | "adoc" => Ok(Self::AsciiDoc),
+            "rst" | "restructuredtext" => Ok(Self::Rst),
+            "text" | "txt" | "plain" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => bail!("Unknown output format '{}'", other),
+        }
+    }
+}
+
+/// JSON schema passed as Ollama's `format` parameter.
+pub fn schema() -> serde_json::Value {
+    let items = json!({
+        "type": "array",
+        "items": {
+            "type": "object",
+            "properties": {
+                "title": { "type": "string" },
+                "description": { "type": "string" },
+                "references": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["title", "description"]
+        }
+    });
+    json!({
+        "type": "object",
+        "properties": {
+            "title": { "type": "string" },
+            "summary": { "type": "string" },
+            "features": items,
+            "improvements": items,
+            "fixes": items,
+            "security": items,
+            "deprecations": items
+        },
+        "required": ["summary", "features", "improvements", "fixes", "security", "deprecations"]
+    })
+}
+
+const JSON_INSTRUCTIONS: &str = r#"Respond ONLY with a JSON object using these fields:
+- "title": product name and version, if known
+- "summary": 2-4 sentence executive summary
+- "features", "improvements", "fixes", "security", "deprecations": arrays of
+  { "title": short name, "description": user-facing benefit, "references": issue keys or CVE IDs }
+Use an empty array for sections with no content."#;
+
+impl ReleaseNotes {
+    /// Parses model output and checks it is usable: a non-empty summary, no
+    /// empty items, and references that look like issue keys or CVE IDs.
+    pub fn parse(text: &str) -> Result<Self> {
+        let notes: ReleaseNotes = serde_json::from_str(text.trim())
+            .context("Response is not valid release notes JSON")?;
+        notes.validate()?;
+        Ok(notes)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.summary.trim().is_empty() {
+            bail!("'summary' must not be empty");
+        }
+        let reference = Regex::new(r"^(CVE-\d{4}-\d+|[A-Z][A-Z0-9]+-\d+|#\d+)$").unwrap();
+        for (section, items) in self.sections() {
+            for (i, item) in items.iter().enumerate() {
+                if item.title.trim().is_empty() || item.description.trim().is_empty() {
+                    bail!("'{}[{}]' needs both a title and a description", section, i);
+                }
+                if let Some(bad) = item.references.iter().find(|r| !reference.is_match(r)) {
+                    bail!(
+                        "'{}[{}]' has an invalid reference '{}' (expected e.g. PROJ-123 or CVE-2024-1234)",
+                        section,
+                        i,
+                        bad
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn sections(&self) -> [(&'static str, &Vec<NoteItem>); 5] {
+        [
+            ("features", &self.features),
+            ("improvements", &self.improvements),
+            ("fixes", &self.fixes),
+            ("security", &self.security),
+            ("deprecations", &self.deprecations),
+        ]
+    }
+
+    fn headed_sections(&self) -> [(&'static str, &Vec<NoteItem>); 5] {
+        [
+            ("New Features", &self.features),
+            ("Improvements", &self.improvements),
+            ("Bug Fixes", &self.fixes),
+            ("Security Notes", &self.security),
+            ("Deprecations / Removed Functionality", &self.deprecations),
+        ]
+    }
+
+    pub fn render(&self, format: NotesFormat) -> String {
+        match format {
+            NotesFormat::Markdown => self.to_markdown(),
+            NotesFormat::Html => render::with_theme(&self.to_html(), None),
+            NotesFormat::AsciiDoc => render::to_asciidoc(&self.to_markdown()),
+            NotesFormat::Rst => render::to_rst(&self.to_markdown()),
+            NotesFormat::Text => render::to_plain_text(&self.to_markdown()),
+            NotesFormat::Json => serde_json::to_string_pretty(self).unwrap_or_default(),
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        if let Some(title) = &self.title {
+            let _ = writeln!(out, "# {}\n", title);
+        }
+        let _ = writeln!(out, "## Executive Summary\n{}\n", self.summary.trim());
+        for (heading, items) in self.headed_sections() {
+            let _ = writeln!(out, "## {}", heading);
+            if items.is_empty() {
+                let _ = writeln!(out, "None in this release.");
+            }
+            for item in items {
+                let _ = write!(out, "- **{}** — {}", item.title, item.description);
+                if !item.references.is_empty() {
+                    let _ = write!(out, " ({})", item.references.join(", "));
+                }
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+        out.trim_end().to_string() + "\n"
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut out = String::from("<article class=\"release-notes\">\n");
+        if let Some(title) = &self.title {
+            let _ = writeln!(out, "<h1>{}</h1>", escape_html(title));
+        }
+        let _ = writeln!(
+            out,
+            "<h2>Executive Summary</h2>\n<p>{}</p>",
+            escape_html(self.summary.trim())
+        );
+        for (heading, items) in self.headed_sections() {
+            let _ = writeln!(out, "<h2>{}</h2>", escape_html(heading));
+            if items.is_empty() {
+                let _ = writeln!(out, "<p>None in this release.</p>");
+                continue;
+            }
+            out.push_str("<ul>\n");
+            for item in items {
+                let _ = write!(
+                    out,
+                    "<li><strong>{}</strong> — {}",
+                    escape_html(&item.title),
+                    escape_html(&item.description)
+                );
+                if !item.references.is_empty() {
+                    let _ = write!(out, " ({})", escape_html(&item.references.join(", ")));
+                }
+                out.push_str("</li>\n");
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</article>\n");
+        out
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Result of [`generate_structured`].
+#[derive(Clone, Debug)]
+pub struct StructuredGeneration {
+    pub notes: ReleaseNotes,
+    /// Raw JSON of the accepted response.
+    pub raw: String,
+    pub attempts: usize,
+    pub stats: Option<OllamaStats>,
+}
+
+/// Asks the model for release notes as JSON matching [`schema`], retrying with
+/// the validation error as feedback up to `max_retries` times.
+///
+/// Returns `Ok(None)` if `cancel` fires.
+pub async fn generate_structured(
+    model: &str,
+    url: &str,
+    context: &str,
+    system: Option<&String>,
+    options: &OllamaOptions,
+    max_retries: usize,
+    cancel: Option<&CancellationToken>,
+) -> Result<Option<StructuredGeneration>> {
+    let options = OllamaOptions {
+        format: Some(schema()),
+        ..options.clone()
+    };
+    let base_prompt = format!("{}\n\n---\n{}", context, JSON_INSTRUCTIONS);
+    let mut prompt = base_prompt.clone();
+    let mut last_error = String::new();
+
+    for attempt in 1..=max_retries + 1 {
+        let response = call_ollama(
+            model,
+            url,
+            &prompt,
+            system,
+            &options,
+            None::<fn(&str)>,
+            cancel,
+        )
+        .await?;
+        if response.cancelled {
+            return Ok(None);
+        }
+
+        match ReleaseNotes::parse(&response.text) {
+            Ok(notes) => {
+                return Ok(Some(StructuredGeneration {
+                    notes,
+                    raw: response.text,
+                    attempts: attempt,
+                    stats: response.stats,
+                }))
+            }
+            Err(e) => {
+                last_error = format!("{:#}", e);
+                println!(
+                    "Attempt {} returned invalid release notes: {}",
+                    attempt, last_error
+                );
+                prompt = format!(
+                    "{}\n\nYour previous answer was rejected: {}\nAnswer again with corrected JSON only.",
+                    base_prompt, last_error
+                );
+            }
+        }
+    }
+
+    bail!(
+        "Model did not return valid release notes after {} attempt(s): {}",
+        max_retries + 1,
+        last_error
+    )
+}
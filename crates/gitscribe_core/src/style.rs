@@ -0,0 +1,350 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::git_notes::{self, NotesStore};
+use crate::run_git_command;
+
+/// Number of previous releases shown when none is configured.
+pub const DEFAULT_STYLE_COUNT: usize = 3;
+/// Characters of examples shown when no limit is configured.
+pub const DEFAULT_STYLE_MAX_CHARS: usize = 6000;
+/// Tags looked at for stored notes before giving up.
+const MAX_TAGS_SCANNED: usize = 50;
+
+const NOTES_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// Where previous release notes are read from, and how much of them to show.
+/// Set globally by the CLI or desktop app and per template in its settings
+/// block (see [`crate::templates::parse_template`]).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StyleSettings {
+    /// A changelog file, a directory of notes, `git-notes[:REF]`, or `none`.
+    pub source: Option<String>,
+    /// How many previous releases to show.
+    pub count: Option<usize>,
+    /// Upper limit for all examples together, in characters.
+    pub max_chars: Option<usize>,
+}
+
+impl StyleSettings {
+    /// Returns `self` with every value set in `overrides` replaced.
+    pub fn merge(mut self, overrides: StyleSettings) -> Self {
+        if let Some(source) = overrides.source.filter(|s| !s.trim().is_empty()) {
+            self.source = Some(source);
+        }
+        self.count = overrides.count.or(self.count);
+        self.max_chars = overrides.max_chars.or(self.max_chars);
+        self
+    }
+}
+
+/// The settings for a run: `defaults` overridden by each template's settings
+/// block. The examples are part of the context, which all templates of a run
+/// share, so the templates must agree on them.
+pub fn settings_for_templates(
+    defaults: &StyleSettings,
+    templates: &[&str],
+) -> Result<StyleSettings> {
+    let mut resolved: Option<StyleSettings> = None;
+    for template in templates {
+        let (settings, _) = crate::templates::parse_template(template)?;
+        let merged = defaults.clone().merge(settings.style);
+        match &resolved {
+            Some(earlier) if *earlier != merged => bail!(
+                "The templates set different style examples, but share one context; generate them in separate runs"
+            ),
+            Some(_) => {}
+            None => resolved = Some(merged),
+        }
+    }
+    Ok(resolved.unwrap_or_else(|| defaults.clone()))
+}
+
+/// Notes of one previous release.
+#[derive(Clone, Debug, Serialize)]
+pub struct StyleExample {
+    /// Where the notes came from, e.g. "v1.2.0 (git notes)".
+    pub label: String,
+    pub text: String,
+}
+
+enum StyleSource {
+    GitNotes(String),
+    Path(PathBuf),
+}
+
+impl StyleSource {
+    fn parse(spec: &str, cwd: Option<&Path>) -> Option<Self> {
+        let spec = spec.trim();
+        if spec.is_empty() || spec.eq_ignore_ascii_case("none") {
+            return None;
+        }
+        if spec == "git-notes" {
+            return Some(Self::GitNotes(git_notes::DEFAULT_NOTES_REF.to_string()));
+        }
+        if let Some(notes_ref) = spec.strip_prefix("git-notes:") {
+            return Some(Self::GitNotes(notes_ref.to_string()));
+        }
+        let path = PathBuf::from(spec);
+        Some(Self::Path(match cwd {
+            Some(cwd) if path.is_relative() => cwd.join(path),
+            _ => path,
+        }))
+    }
+}
+
+/// Release sections of a Keep a Changelog style file: one per `## ` heading,
+/// newest first, without "Unreleased".
+fn changelog_sections(changelog: &str, file_name: &str) -> Vec<StyleExample> {
+    let mut sections: Vec<StyleExample> = Vec::new();
+    let mut in_code = false;
+    for line in changelog.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        match line.strip_prefix("## ").filter(|_| !in_code) {
+            Some(heading) => sections.push(StyleExample {
+                label: format!("{}: {}", file_name, heading.trim()),
+                text: String::new(),
+            }),
+            None => match sections.last_mut() {
+                Some(section) => {
+                    section.text.push_str(line);
+                    section.text.push('\n');
+                }
+                None => continue,
+            },
+        }
+    }
+    sections
+        .into_iter()
+        .filter(|s| !s.label.to_lowercase().contains("unreleased"))
+        .filter(|s| !s.text.trim().is_empty())
+        .map(|s| StyleExample {
+            text: s.text.trim().to_string(),
+            ..s
+        })
+        .collect()
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum NameChunk {
+    Number(u64),
+    Text(String),
+}
+
+/// Sort key that orders `v1.10.md` after `v1.9.md`.
+fn version_key(name: &str) -> Vec<NameChunk> {
+    let mut chunks = Vec::new();
+    let mut rest = name;
+    while let Some(first) = rest.chars().next() {
+        let digits = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(match chunk.parse() {
+            Ok(number) if digits => NameChunk::Number(number),
+            _ => NameChunk::Text(chunk.to_lowercase()),
+        });
+        rest = tail;
+    }
+    chunks
+}
+
+/// Notes files in `dir`, newest version first by file name.
+fn directory_notes(dir: &Path, count: usize) -> Result<Vec<StyleExample>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).context(format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let is_notes = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| NOTES_EXTENSIONS.contains(&e));
+        if path.is_file() && is_notes {
+            files.push(path);
+        }
+    }
+    files.sort_by_key(|path| {
+        Reverse(version_key(
+            &path.file_stem().unwrap_or_default().to_string_lossy(),
+        ))
+    });
+
+    let mut examples = Vec::new();
+    for path in files.into_iter().take(count) {
+        let text =
+            fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+        if !text.trim().is_empty() {
+            examples.push(StyleExample {
+                label: path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                text: text.trim().to_string(),
+            });
+        }
+    }
+    Ok(examples)
+}
+
+/// Notes stored for the tags reachable from `start`, newest version first.
+fn stored_notes(
+    start: &str,
+    notes_ref: &str,
+    count: usize,
+    cwd: Option<&Path>,
+) -> Result<Vec<StyleExample>> {
+    let tags = run_git_command(&["tag", "--merged", start, "--sort=-v:refname"], cwd)?;
+    let examples = tags
+        .lines()
+        .take(MAX_TAGS_SCANNED)
+        .filter_map(|tag| {
+            let stored = git_notes::read_stored_notes(tag, notes_ref, cwd)?;
+            let store = match stored.store {
+                NotesStore::GitNotes => "git notes",
+                NotesStore::Tag => "tag message",
+            };
+            Some(StyleExample {
+                label: format!("{} ({})", tag, store),
+                text: stored.text,
+            })
+        })
+        .take(count)
+        .collect();
+    Ok(examples)
+}
+
+/// Keeps whole lines of `text` up to `max_chars`, marking the cut.
+fn truncate_lines(text: &str, max_chars: usize) -> Option<String> {
+    let mut kept = String::new();
+    let mut used = 0;
+    for line in text.lines() {
+        let len = line.chars().count() + 1;
+        if used + len > max_chars {
+            break;
+        }
+        kept.push_str(line);
+        kept.push('\n');
+        used += len;
+    }
+    (!kept.trim().is_empty()).then(|| format!("{}[...]", kept))
+}
+
+/// Notes of up to `count` previous releases from the configured source,
+/// newest first and cut to `max_chars` in total. Returns nothing when no
+/// source is set.
+pub fn load_examples(
+    settings: &StyleSettings,
+    start: &str,
+    cwd: Option<&Path>,
+) -> Result<Vec<StyleExample>> {
+    let Some(source) = settings
+        .source
+        .as_deref()
+        .and_then(|spec| StyleSource::parse(spec, cwd))
+    else {
+        return Ok(Vec::new());
+    };
+    let count = settings.count.unwrap_or(DEFAULT_STYLE_COUNT);
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let found = match source {
+        StyleSource::GitNotes(notes_ref) => stored_notes(start, &notes_ref, count, cwd)?,
+        StyleSource::Path(path) if path.is_dir() => directory_notes(&path, count)?,
+        StyleSource::Path(path) => {
+            let changelog =
+                fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            changelog_sections(&changelog, &file_name)
+                .into_iter()
+                .take(count)
+                .collect()
+        }
+    };
+
+    let mut budget = settings.max_chars.unwrap_or(DEFAULT_STYLE_MAX_CHARS);
+    let mut examples = Vec::new();
+    for mut example in found {
+        let len = example.text.chars().count();
+        if len > budget {
+            match truncate_lines(&example.text, budget) {
+                Some(text) => example.text = text,
+                None => break,
+            }
+            examples.push(example);
+            break;
+        }
+        budget -= len;
+        examples.push(example);
+    }
+    Ok(examples)
+}
+
+/// The "Style Examples" section of the built-in context layout, asking the
+/// model to match the examples' voice but not their content. Empty without
+/// examples.
+pub fn examples_section(examples: &[StyleExample]) -> String {
+    if examples.is_empty() {
+        return String::new();
+    }
+    let mut section = "\n## Style Examples\n\nThese are earlier release notes of this project. Match their tone, voice, structure and level of detail. Do not copy their content: describe only the changes of this release.\n".to_string();
+    for example in examples {
+        section.push_str(&format!(
+            "\n<example source=\"{}\">\n{}\n</example>\n",
+            example.label, example.text
+        ));
+    }
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> StyleSettings {
+        StyleSettings {
+            source: Some("CHANGELOG.md".to_string()),
+            count: Some(2),
+            max_chars: None,
+        }
+    }
+
+    #[test]
+    fn templates_override_defaults() {
+        let settings =
+            settings_for_templates(&defaults(), &["---\nstyle-count: 5\n---\n# Prompt\n"]).unwrap();
+        assert_eq!(settings.source.as_deref(), Some("CHANGELOG.md"));
+        assert_eq!(settings.count, Some(5));
+        assert_eq!(
+            settings_for_templates(&defaults(), &[]).unwrap(),
+            defaults()
+        );
+    }
+
+    #[test]
+    fn templates_must_agree() {
+        let plain = "# Prompt\n";
+        let same = "---\nstyle-examples: CHANGELOG.md\n---\n# Prompt\n";
+        let other = "---\nstyle-examples: none\n---\n# Prompt\n";
+        assert!(settings_for_templates(&defaults(), &[plain, same]).is_ok());
+        assert!(settings_for_templates(&defaults(), &[plain, other]).is_err());
+    }
+
+    #[test]
+    fn section_lists_examples() {
+        assert_eq!(examples_section(&[]), "");
+        let section = examples_section(&[StyleExample {
+            label: "v1.0.0".to_string(),
+            text: "- Added export".to_string(),
+        }]);
+        assert!(section.starts_with("\n## Style Examples\n"));
+        assert!(section.ends_with("<example source=\"v1.0.0\">\n- Added export\n</example>\n"));
+    }
+}
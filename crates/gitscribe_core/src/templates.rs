@@ -0,0 +1,235 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::style::StyleSettings;
+
+/// Template used when none is selected.
+pub const DEFAULT_TEMPLATE: &str = "end-user";
+
+/// Prompt templates shipped with GitScribe, by name.
+pub const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("end-user", include_str!("../templates/end-user.md")),
+    ("admin", include_str!("../templates/admin.md")),
+    (
+        "internal-changelog",
+        include_str!("../templates/internal-changelog.md"),
+    ),
+    (
+        "security-advisory",
+        include_str!("../templates/security-advisory.md"),
+    ),
+    ("marketing", include_str!("../templates/marketing.md")),
+];
+
+const TEMPLATE_EXTENSIONS: &[&str] = &["md", "txt"];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TemplateInfo {
+    pub name: String,
+    /// False for templates in the user directory, including ones that
+    /// override a built-in of the same name.
+    pub builtin: bool,
+}
+
+/// Keys of the settings block, see [`TemplateSettings`].
+const SETTING_KEYS: &[&str] = &["style-examples", "style-count", "style-max-chars"];
+
+/// Settings a template can give in a leading block of `key: value` lines
+/// between `---` lines.
+#[derive(Clone, Debug, Default)]
+pub struct TemplateSettings {
+    /// `style-examples`, `style-count` and `style-max-chars`.
+    pub style: StyleSettings,
+}
+
+impl TemplateSettings {
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let number = || {
+            value
+                .parse::<usize>()
+                .context(format!("Template setting '{}' must be a number", key))
+        };
+        match key {
+            "style-examples" => self.style.source = Some(value.to_string()),
+            "style-count" => self.style.count = Some(number()?),
+            "style-max-chars" => self.style.max_chars = Some(number()?),
+            other => bail!(
+                "Unknown template setting '{}' (expected style-examples, style-count or style-max-chars)",
+                other
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// Splits a template into its settings block and the prompt itself.
+/// Templates that do not start with a block of known `key: value` settings,
+/// such as prompts with their own front matter, come back unchanged.
+pub fn parse_template(template: &str) -> Result<(TemplateSettings, &str)> {
+    let mut settings = TemplateSettings::default();
+    let mut lines = template.split_inclusive('\n');
+    let Some(first) = lines.next().filter(|l| l.trim_end() == "---") else {
+        return Ok((settings, template));
+    };
+    let mut offset = first.len();
+    let mut pairs = Vec::new();
+    for line in lines {
+        offset += line.len();
+        let line = line.trim();
+        if line == "---" {
+            if pairs.is_empty() {
+                break;
+            }
+            for (key, value) in pairs {
+                settings.set(key, value)?;
+            }
+            return Ok((settings, &template[offset..]));
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once(':') {
+            Some((key, value)) if SETTING_KEYS.contains(&key) => pairs.push((key, value.trim())),
+            _ => break,
+        }
+    }
+    Ok((TemplateSettings::default(), template))
+}
+
+/// Directory for user templates, shared by the CLI and the desktop app
+/// (`~/.config/gitscribe/templates` on Linux). It may not exist yet.
+pub fn user_templates_dir() -> Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .context("Could not determine the config directory")?
+        .join("gitscribe")
+        .join("templates"))
+}
+
+/// Like [`user_templates_dir`], but creates the directory if needed.
+pub fn create_user_templates_dir() -> Result<PathBuf> {
+    let dir = user_templates_dir()?;
+    fs::create_dir_all(&dir).context(format!(
+        "Failed to create templates directory {}",
+        dir.display()
+    ))?;
+    Ok(dir)
+}
+
+fn user_template_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    TEMPLATE_EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .chain(std::iter::once(dir.join(name)))
+        .find(|p| p.is_file())
+}
+
+/// Built-in and user templates, sorted by name. A user template named like
+/// a built-in replaces it.
+pub fn list_templates() -> Result<Vec<TemplateInfo>> {
+    let mut templates: Vec<TemplateInfo> = BUILTIN_TEMPLATES
+        .iter()
+        .map(|(name, _)| TemplateInfo {
+            name: name.to_string(),
+            builtin: true,
+        })
+        .collect();
+
+    let dir = user_templates_dir()?;
+    // Nothing has been saved there yet
+    let entries = if dir.is_dir() {
+        Some(fs::read_dir(&dir).context("Failed to read templates directory")?)
+    } else {
+        None
+    };
+    for entry in entries.into_iter().flatten() {
+        let path = entry?.path();
+        let is_template = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| TEMPLATE_EXTENSIONS.contains(&e));
+        if !path.is_file() || !is_template {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        match templates.iter_mut().find(|t| t.name == name) {
+            Some(existing) => existing.builtin = false,
+            None => templates.push(TemplateInfo {
+                name: name.to_string(),
+                builtin: false,
+            }),
+        }
+    }
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+/// Loads a template by name, preferring the user directory over built-ins.
+pub fn load_template(name: &str) -> Result<String> {
+    if name.contains(['/', '\\']) || name.starts_with('.') {
+        bail!("Invalid template name '{}'", name);
+    }
+    let dir = user_templates_dir()?;
+    if let Some(path) = user_template_path(&dir, name) {
+        return fs::read_to_string(&path)
+            .context(format!("Failed to read template {}", path.display()));
+    }
+    if let Some((_, content)) = BUILTIN_TEMPLATES.iter().find(|(n, _)| *n == name) {
+        return Ok(content.to_string());
+    }
+
+    let available: Vec<String> = list_templates()?.into_iter().map(|t| t.name).collect();
+    bail!(
+        "Unknown template '{}'. Available templates: {}",
+        name,
+        available.join(", ")
+    )
+}
+
+/// Copies templates from an older location into the shared directory,
+/// keeping any that already exist there. Returns how many were copied.
+pub fn import_templates(from: &Path) -> Result<usize> {
+    if !from.is_dir() {
+        return Ok(0);
+    }
+    let dir = create_user_templates_dir()?;
+    let mut copied = 0;
+    for entry in fs::read_dir(from)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name() else {
+            continue;
+        };
+        let target = dir.join(file_name);
+        if path.is_file() && !target.exists() {
+            fs::copy(&path, &target)?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_settings_block() {
+        let (settings, body) =
+            parse_template("---\nstyle-examples: none\nstyle-count: 2\n---\n# Prompt\n").unwrap();
+        assert_eq!(settings.style.source.as_deref(), Some("none"));
+        assert_eq!(settings.style.count, Some(2));
+        assert_eq!(body, "# Prompt\n");
+    }
+
+    #[test]
+    fn keeps_block_with_unknown_keys() {
+        let template = "---\ntitle: Foo\nstyle-count: 2\n---\n# Prompt\n";
+        let (settings, body) = parse_template(template).unwrap();
+        assert!(settings.style.count.is_none());
+        assert_eq!(body, template);
+    }
+}
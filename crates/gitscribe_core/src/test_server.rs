@@ -0,0 +1,78 @@
+//! A stand-in HTTP server for tests of the modules that talk to web services.
+
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// A request received by the stand-in server.
+pub struct Received {
+    /// e.g. "POST /repos/owner/name/releases HTTP/1.1"
+    pub line: String,
+    pub head: String,
+    pub body: String,
+}
+
+async fn read_request(stream: &mut TcpStream) -> Received {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut buf).await.unwrap();
+        assert!(n > 0, "connection closed mid-request");
+        data.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&data).to_string();
+        let Some(end) = text.find("\r\n\r\n") else {
+            continue;
+        };
+        let head = text[..end].to_string();
+        let length = head
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().unwrap())
+            })
+            .unwrap_or(0);
+        if data.len() >= end + 4 + length {
+            return Received {
+                line: head.lines().next().unwrap_or_default().to_string(),
+                body: text[end + 4..].to_string(),
+                head,
+            };
+        }
+    }
+}
+
+/// Answers each request with the first route whose prefix matches its
+/// request line, or 404. Returns the base URL and the requests received.
+pub async fn serve(routes: Vec<(&'static str, u16, Value)>) -> (String, Arc<Mutex<Vec<Received>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let log = received.clone();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let request = read_request(&mut stream).await;
+            let (status, body) = routes
+                .iter()
+                .find(|(prefix, _, _)| request.line.starts_with(prefix))
+                .map(|(_, status, body)| (*status, body.to_string()))
+                .unwrap_or((404, "{}".to_string()));
+            log.lock().unwrap().push(request);
+            let response = format!(
+                "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    (url, received)
+}
+
+/// A local URL nothing listens on, for testing connection errors.
+pub async fn closed_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
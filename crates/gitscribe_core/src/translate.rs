@@ -0,0 +1,258 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use tokio_util::sync::CancellationToken;
+
+use crate::ollama::{call_ollama, OllamaOptions, OllamaStats};
+
+const TRANSLATION_PROMPT: &str = r#"You are a professional technical translator.
+Translate the release notes you are given from English into {language}.
+
+Rules:
+- Keep the Markdown structure exactly: the same headings, list items, bold and italic text, links and blank lines, in the same order.
+- Translate only human-readable text.
+- Tokens like ⟦3⟧ stand for product names, issue keys, CVE IDs, code and URLs. Copy every one of them unchanged, exactly once, where it belongs in the translated sentence.
+- Do not add, drop or summarize content, and do not add notes about the translation.
+
+Respond with the translated Markdown only."#;
+
+/// Product terms that must survive translation verbatim.
+#[derive(Clone, Debug, Default)]
+pub struct Glossary {
+    pub terms: Vec<String>,
+}
+
+impl Glossary {
+    /// One term per line; blank lines and lines starting with `#` are ignored.
+    pub fn parse(text: &str) -> Self {
+        let terms = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        Self { terms }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .context(format!("Failed to read glossary {}", path.display()))?;
+        Ok(Self::parse(&text))
+    }
+}
+
+/// English name of a locale such as `de` or `pt-BR`, for the prompt.
+/// Unknown locales are passed to the model as given.
+pub fn language_name(locale: &str) -> String {
+    let language = locale.split(['-', '_']).next().unwrap_or(locale);
+    let name = match language.to_ascii_lowercase().as_str() {
+        "de" => "German",
+        "fr" => "French",
+        "ja" => "Japanese",
+        "es" => "Spanish",
+        "it" => "Italian",
+        "pt" => "Portuguese",
+        "nl" => "Dutch",
+        "pl" => "Polish",
+        "sv" => "Swedish",
+        "da" => "Danish",
+        "fi" => "Finnish",
+        "no" | "nb" => "Norwegian",
+        "cs" => "Czech",
+        "ru" => "Russian",
+        "uk" => "Ukrainian",
+        "tr" => "Turkish",
+        "zh" => "Chinese",
+        "ko" => "Korean",
+        _ => return locale.to_string(),
+    };
+    if language.len() < locale.len() {
+        format!("{} ({})", name, locale)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Text with protected spans replaced by numbered `⟦n⟧` tokens.
+#[derive(Clone, Debug)]
+pub struct Masked {
+    pub text: String,
+    pub spans: Vec<String>,
+}
+
+fn token(index: usize) -> String {
+    format!("⟦{}⟧", index)
+}
+
+/// Replaces code, URLs, CVE IDs, issue keys and glossary terms with tokens
+/// the model is told to copy unchanged.
+pub fn mask(text: &str, glossary: &Glossary) -> Masked {
+    let mut terms: Vec<&String> = glossary.terms.iter().collect();
+    // Longest first, so "Acme Cloud" wins over "Acme"
+    terms.sort_by_key(|t| std::cmp::Reverse(t.len()));
+    let mut patterns = vec![
+        r"(?s)```.*?```".to_string(),
+        r"`[^`\n]+`".to_string(),
+        r"https?://[^\s)>\]]+".to_string(),
+        r"\bCVE-\d{4}-\d{4,}\b".to_string(),
+        r"\b[A-Z][A-Z0-9]+-\d+\b".to_string(),
+    ];
+    patterns.extend(terms.iter().map(|term| {
+        // \b only applies next to word characters, e.g. not after "C++"
+        let edge = |c: Option<char>| match c {
+            Some(c) if c.is_alphanumeric() || c == '_' => r"\b",
+            _ => "",
+        };
+        format!(
+            "{}{}{}",
+            edge(term.chars().next()),
+            regex::escape(term),
+            edge(term.chars().last())
+        )
+    }));
+    let protected = Regex::new(&patterns.join("|")).expect("valid mask pattern");
+
+    let mut spans = Vec::new();
+    let text = protected
+        .replace_all(text, |caps: &regex::Captures| {
+            spans.push(caps[0].to_string());
+            token(spans.len() - 1)
+        })
+        .into_owned();
+    Masked { text, spans }
+}
+
+/// Puts the protected spans back. Returns the text and the spans whose
+/// token the model dropped.
+pub fn unmask(text: &str, spans: &[String]) -> (String, Vec<String>) {
+    let mut result = text.to_string();
+    let mut missing = Vec::new();
+    for (index, span) in spans.iter().enumerate() {
+        let token = token(index);
+        if result.contains(&token) {
+            result = result.replace(&token, span);
+        } else {
+            missing.push(span.clone());
+        }
+    }
+    (result, missing)
+}
+
+/// Headings and list items, which a faithful translation keeps one-to-one.
+fn outline(markdown: &str) -> (Vec<usize>, usize) {
+    let mut headings = Vec::new();
+    let mut items = 0;
+    let mut in_code = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        if level > 0 && trimmed[level..].starts_with(' ') {
+            headings.push(level);
+        } else if trimmed.starts_with("- ")
+            || trimmed.starts_with("* ")
+            || trimmed
+                .split_once(". ")
+                .is_some_and(|(n, _)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        {
+            items += 1;
+        }
+    }
+    (headings, items)
+}
+
+/// Differences in Markdown structure between the original and a translation.
+pub fn structure_problems(original: &str, translated: &str) -> Vec<String> {
+    let (headings, items) = outline(original);
+    let (translated_headings, translated_items) = outline(translated);
+    let mut problems = Vec::new();
+    if headings.len() != translated_headings.len() {
+        problems.push(format!(
+            "Headings changed: {} in the original, {} in the translation",
+            headings.len(),
+            translated_headings.len()
+        ));
+    } else if headings != translated_headings {
+        problems.push("Heading levels differ from the original".to_string());
+    }
+    if items != translated_items {
+        problems.push(format!(
+            "List items changed: {} in the original, {} in the translation",
+            items, translated_items
+        ));
+    }
+    problems
+}
+
+/// Outcome of translating into one locale.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TranslationCheck {
+    pub locale: String,
+    /// Structure changes and protected terms the model dropped.
+    pub problems: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Translation {
+    pub text: String,
+    pub check: TranslationCheck,
+    pub stats: Option<OllamaStats>,
+}
+
+/// Translates Markdown notes into `locale`, keeping issue keys, CVE IDs,
+/// code, URLs and glossary terms verbatim.
+///
+/// Returns `Ok(None)` if `cancel` fires.
+pub async fn translate_notes(
+    model: &str,
+    url: &str,
+    notes: &str,
+    locale: &str,
+    glossary: &Glossary,
+    options: &OllamaOptions,
+    cancel: Option<&CancellationToken>,
+) -> Result<Option<Translation>> {
+    let masked = mask(notes, glossary);
+    let system = TRANSLATION_PROMPT.replace("{language}", &language_name(locale));
+    let options = OllamaOptions {
+        format: None,
+        ..options.clone()
+    };
+    let response = call_ollama(
+        model,
+        url,
+        &masked.text,
+        Some(&system),
+        &options,
+        None::<fn(&str)>,
+        cancel,
+    )
+    .await?;
+    if response.cancelled {
+        return Ok(None);
+    }
+
+    let (text, missing) = unmask(response.text.trim(), &masked.spans);
+    let mut problems = structure_problems(notes, &text);
+    problems.extend(
+        missing
+            .iter()
+            .map(|span| format!("Dropped protected text: {}", span)),
+    );
+    Ok(Some(Translation {
+        text: text + "\n",
+        check: TranslationCheck {
+            locale: locale.to_string(),
+            problems,
+        },
+        stats: response.stats,
+    }))
+}
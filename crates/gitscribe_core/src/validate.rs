@@ -0,0 +1,169 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::jira::extract_issue_keys;
+
+/// Phrases that should never reach published notes: model preambles and leftovers.
+pub const DEFAULT_FORBIDDEN_PHRASES: &[&str] = &[
+    "As an AI",
+    "As a language model",
+    "Here are the release notes",
+    "I cannot",
+    "TODO",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    MissingHeading,
+    ForbiddenPhrase,
+    Placeholder,
+    UnknownIssue,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// What generated notes are checked against, usually derived from the
+/// output template embedded in the system prompt.
+#[derive(Clone, Debug, Default)]
+pub struct ValidationRules {
+    pub required_headings: Vec<String>,
+    pub forbidden_phrases: Vec<String>,
+    pub placeholders: Vec<String>,
+}
+
+/// Returns the body of the first fenced ```markdown block in `prompt`, which is
+/// where the built-in prompts put their output template.
+pub fn extract_output_template(prompt: &str) -> Option<&str> {
+    let start = prompt.find("```markdown")?;
+    let body = &prompt[start + "```markdown".len()..];
+    let end = body.find("\n```")?;
+    Some(body[..end].trim_matches('\n'))
+}
+
+/// Heading text without `#`s and trailing qualifiers like "(User-Relevant Only)".
+fn normalize_heading(line: &str) -> String {
+    let text = line.trim_start_matches('#').trim();
+    let text = match text.find(" (") {
+        Some(pos) => &text[..pos],
+        None => text,
+    };
+    text.trim().to_lowercase()
+}
+
+impl ValidationRules {
+    /// Builds rules from the system prompt's output template. Without a
+    /// template only the forbidden phrases and issue keys are checked.
+    pub fn from_prompt(system_prompt: Option<&str>) -> Self {
+        let mut rules = ValidationRules {
+            forbidden_phrases: DEFAULT_FORBIDDEN_PHRASES
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..Default::default()
+        };
+
+        let Some(template) = system_prompt.and_then(extract_output_template) else {
+            return rules;
+        };
+
+        for line in template.lines() {
+            // Title lines such as "# [Product Name] [Version]" are placeholders themselves
+            if line.starts_with("##") && !line.contains('[') {
+                rules
+                    .required_headings
+                    .push(line.trim_start_matches('#').trim().to_string());
+            }
+        }
+
+        let placeholder = Regex::new(r"\[[^\[\]\n]{2,120}\]").unwrap();
+        for m in placeholder.find_iter(template) {
+            let text = m.as_str().to_string();
+            if !rules.placeholders.contains(&text) {
+                rules.placeholders.push(text);
+            }
+        }
+
+        rules
+    }
+
+    /// Drops the heading checks, for notes rendered from structured output:
+    /// their sections come from the schema, not from the template.
+    pub fn without_headings(mut self) -> Self {
+        self.required_headings.clear();
+        self
+    }
+
+    pub fn validate(&self, output: &str, context: &str) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        let headings: Vec<String> = output
+            .lines()
+            .filter(|l| l.trim_start().starts_with('#'))
+            .map(normalize_heading)
+            .collect();
+        for required in &self.required_headings {
+            let wanted = normalize_heading(required);
+            if !headings.iter().any(|h| h.starts_with(&wanted)) {
+                violations.push(Violation {
+                    kind: ViolationKind::MissingHeading,
+                    message: format!("Missing required section \"{}\"", required),
+                });
+            }
+        }
+
+        let lower = output.to_lowercase();
+        for phrase in &self.forbidden_phrases {
+            if lower.contains(&phrase.to_lowercase()) {
+                violations.push(Violation {
+                    kind: ViolationKind::ForbiddenPhrase,
+                    message: format!("Contains forbidden phrase \"{}\"", phrase),
+                });
+            }
+        }
+
+        for placeholder in &self.placeholders {
+            if output.contains(placeholder.as_str()) {
+                violations.push(Violation {
+                    kind: ViolationKind::Placeholder,
+                    message: format!("Template placeholder {} was not filled in", placeholder),
+                });
+            }
+        }
+
+        let known: HashSet<String> = extract_issue_keys(context).into_iter().collect();
+        for key in extract_issue_keys(output) {
+            if !key.starts_with("CVE-") && !known.contains(&key) {
+                violations.push(Violation {
+                    kind: ViolationKind::UnknownIssue,
+                    message: format!("Cites issue {} which does not appear in the context", key),
+                });
+            }
+        }
+
+        violations
+    }
+}
+
+/// Instruction for a repair pass that asks the model to fix the listed problems.
+pub fn repair_instruction(violations: &[Violation]) -> String {
+    let problems: Vec<String> = violations.iter().map(|v| format!("- {}", v)).collect();
+    format!(
+        "Your release notes do not follow the required template:\n{}\n\n\
+         Rewrite the complete release notes fixing these problems. Only cite issue keys that \
+         appear in the provided data. Output only the corrected release notes.",
+        problems.join("\n")
+    )
+}